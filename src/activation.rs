@@ -0,0 +1,361 @@
+use crate::{
+    features::{FeatureEntry, Features},
+    resolve::Resolver,
+};
+use cargo_metadata::{DependencyKind, Metadata, NodeDep, Package, PackageId};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+/// The features of a dependency that are actually enabled in a workspace's build
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ActivatedFeatures {
+    /// The name of the dependency
+    pub name: String,
+    /// The version of the dependency that was resolved
+    pub version: String,
+    /// Each enabled feature, and the packages that requested it
+    pub enabled: BTreeMap<String, Vec<Requester>>,
+    /// The features that are available, but not enabled
+    pub disabled: BTreeSet<String>,
+}
+
+/// A package whose `Cargo.toml` enabled a feature on a dependency
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+pub struct Requester {
+    /// The name of the package
+    pub name: String,
+    /// The version of the package
+    pub version: String,
+    /// The feature of the package that enabled it, if it wasn't enabled on the dependency itself
+    pub via: Option<String>,
+}
+
+impl ActivatedFeatures {
+    /// Resolve the workspace at this path, and find the enabled features for every version of this dependency
+    ///
+    /// This runs the full `cargo metadata` resolution, so it may need to update the lock file (unless `offline` is set)
+    ///
+    /// Only the dependencies that apply to the target are resolved, this is the host if no target is provided
+    pub fn for_dependency(
+        path: &Path,
+        crate_name: &str,
+        offline: bool,
+        target: Option<&str>,
+    ) -> anyhow::Result<Vec<Self>> {
+        let mut cmd = cargo_metadata::MetadataCommand::new();
+        cmd.current_dir(find_dir(path));
+
+        let mut options = vec![];
+        if offline {
+            options.push("--offline".to_string());
+        }
+        if let Some(target) = target.map(String::from).or_else(crate::util::host_triple) {
+            options.extend(["--filter-platform".to_string(), target]);
+        }
+        cmd.other_options(options);
+
+        let metadata = cmd.exec()?;
+        Self::from_metadata(&metadata, crate_name)
+    }
+
+    /// Find the enabled features for every version of this dependency, in already resolved metadata
+    pub fn from_metadata(metadata: &Metadata, crate_name: &str) -> anyhow::Result<Vec<Self>> {
+        let resolve = metadata
+            .resolve
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("cargo did not resolve the dependencies"))?;
+
+        let mut found = resolve
+            .nodes
+            .iter()
+            .filter(|node| metadata[&node.id].name == crate_name)
+            .map(|node| Self::for_node(metadata, &node.id, &node.features))
+            .collect::<Vec<_>>();
+
+        anyhow::ensure!(
+            !found.is_empty(),
+            "'{}' is not a dependency of this workspace",
+            crate_name
+        );
+
+        found.sort_by(|left, right| crate::util::compare_versions(&left.version, &right.version));
+        Ok(found)
+    }
+
+    fn for_node(metadata: &Metadata, id: &PackageId, enabled: &[String]) -> Self {
+        let package = &metadata[id];
+        let features = Features::parse(package.clone());
+
+        let mut requested = BTreeMap::<Requester, BTreeSet<String>>::new();
+        for node in metadata.resolve.iter().flat_map(|resolve| &resolve.nodes) {
+            if let Some(dep) = node.deps.iter().find(|dep| dep.pkg == *id) {
+                let parent = &metadata[&node.id];
+                let member = metadata.workspace_members.contains(&node.id);
+                find_requests(parent, member, &node.features, dep, &mut requested);
+            }
+        }
+
+        let mut activated = BTreeMap::<String, Vec<Requester>>::new();
+        for name in enabled {
+            activated.entry(name.clone()).or_default();
+        }
+
+        // attribute everything that each package's requests transitively enable to that package
+        for (requester, names) in requested {
+            // e.g. `default` is always requested, even if the dependency doesn't have it
+            let names = names
+                .iter()
+                .filter(|name| features.features.contains_key(*name));
+
+            let resolved = match Resolver::new(&features).resolve(names) {
+                Ok(resolved) => resolved,
+                Err(..) => continue,
+            };
+            for name in resolved.features {
+                if let Some(list) = activated.get_mut(&name) {
+                    list.push(requester.clone())
+                }
+            }
+        }
+
+        let disabled = features
+            .features
+            .keys()
+            .filter(|name| !activated.contains_key(*name))
+            .cloned()
+            .collect();
+
+        Self {
+            name: package.name.clone(),
+            version: package.version.to_string(),
+            enabled: activated,
+            disabled,
+        }
+    }
+}
+
+/// Find the features that this package requests from the dependency
+///
+/// Only the dependency entries that cargo resolved (`dep.dep_kinds`) are used, and dev-dependencies
+/// are only used for workspace members
+fn find_requests(
+    parent: &Package,
+    member: bool,
+    parent_features: &[String],
+    dep: &NodeDep,
+    requested: &mut BTreeMap<Requester, BTreeSet<String>>,
+) {
+    // this is the name that cargo resolved the dependency as (it uses `_` rather than `-`)
+    let dep_name = dep.name.as_str();
+    let requester = |via: Option<&str>| Requester {
+        name: parent.name.clone(),
+        version: parent.version.to_string(),
+        via: via.map(ToString::to_string),
+    };
+
+    let matches = |name: &str| name.replace('-', "_") == dep_name;
+
+    let resolved = |entry: &cargo_metadata::Dependency| {
+        (member || entry.kind != DependencyKind::Development)
+            && dep
+                .dep_kinds
+                .iter()
+                .any(|kind| kind.kind == entry.kind && kind.target == entry.target)
+    };
+
+    for dep in &parent.dependencies {
+        if !matches(dep.rename.as_deref().unwrap_or(&dep.name)) || !resolved(dep) {
+            continue;
+        }
+
+        let entry = requested.entry(requester(None)).or_default();
+        if dep.uses_default_features {
+            entry.insert("default".to_string());
+        }
+        entry.extend(dep.features.iter().cloned());
+    }
+
+    // features of the parent can also enable features on the dependency, with `dep/feature`
+    for feature in parent_features {
+        let entries = match parent.features.get(feature) {
+            Some(entries) => entries,
+            None => continue,
+        };
+
+        for entry in entries.iter().map(|s| FeatureEntry::parse(s)) {
+            if let FeatureEntry::DependencyFeature { dep, feature: name }
+            | FeatureEntry::WeakDependencyFeature { dep, feature: name } = entry
+            {
+                if matches(&dep) {
+                    requested
+                        .entry(requester(Some(feature)))
+                        .or_default()
+                        .insert(name);
+                }
+            }
+        }
+    }
+
+    requested.retain(|_, names| !names.is_empty());
+}
+
+/// Find the directory to run `cargo metadata` in, this path can be a directory or a `Cargo.toml`
+fn find_dir(path: &Path) -> &Path {
+    match path.file_name() {
+        Some(name) if name == "Cargo.toml" => path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new(".")),
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(
+        name: &str,
+        features: serde_json::Value,
+        deps: serde_json::Value,
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "version": "1.0.0",
+            "id": format!("{} 1.0.0", name),
+            "dependencies": deps,
+            "features": features,
+            "targets": [],
+            "manifest_path": format!("/{}/Cargo.toml", name),
+        })
+    }
+
+    fn dependency(
+        kind: Option<&str>,
+        target: Option<&str>,
+        default: bool,
+        features: &[&str],
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "name": "lib",
+            "req": "^1",
+            "kind": kind,
+            "target": target,
+            "optional": false,
+            "uses_default_features": default,
+            "features": features,
+        })
+    }
+
+    fn node(name: &str, features: &[&str], dep_kinds: serde_json::Value) -> serde_json::Value {
+        let deps = match dep_kinds {
+            serde_json::Value::Null => serde_json::json!([]),
+            dep_kinds => {
+                serde_json::json!([{ "name": "lib", "pkg": "lib 1.0.0", "dep_kinds": dep_kinds }])
+            }
+        };
+        serde_json::json!({
+            "id": format!("{} 1.0.0", name),
+            "dependencies": [],
+            "deps": deps,
+            "features": features,
+        })
+    }
+
+    // `app` is the only workspace member, and both it and `helper` depend on `lib`.
+    // this was resolved for linux, so only the normal dependencies without a target are in the resolve graph
+    // (and the dev-dependency of `app`). `helper` asks for features that `app` enables, from entries that
+    // cargo didn't use, so it shouldn't be blamed for them
+    //
+    // `g` is never enabled
+    fn metadata() -> Metadata {
+        let lib = package(
+            "lib",
+            serde_json::json!({
+                "default": ["std"], "std": [], "a": [], "b": [], "c": [], "d": [], "e": [], "f": [], "g": []
+            }),
+            serde_json::json!([]),
+        );
+        let app = package(
+            "app",
+            serde_json::json!({ "extra": ["lib/e"] }),
+            serde_json::json!([
+                dependency(None, None, true, &["a", "b", "c", "d"]),
+                dependency(Some("dev"), None, false, &["f"]),
+            ]),
+        );
+        let helper = package(
+            "helper",
+            serde_json::json!({}),
+            serde_json::json!([
+                dependency(None, None, false, &[]),
+                dependency(Some("dev"), None, true, &["b"]),
+                dependency(None, Some("cfg(windows)"), false, &["c"]),
+                dependency(Some("build"), None, false, &["d"]),
+            ]),
+        );
+
+        let json = serde_json::json!({
+            "packages": [lib, app, helper],
+            "workspace_members": ["app 1.0.0"],
+            "resolve": {
+                "root": "app 1.0.0",
+                "nodes": [
+                    node(
+                        "lib",
+                        &["a", "b", "c", "d", "default", "e", "f", "std"],
+                        serde_json::Value::Null,
+                    ),
+                    node("app", &["extra"], serde_json::json!([
+                        { "kind": null, "target": null },
+                        { "kind": "dev", "target": null },
+                    ])),
+                    node("helper", &[], serde_json::json!([{ "kind": null, "target": null }])),
+                ],
+            },
+            "target_directory": "/target",
+            "version": 1,
+            "workspace_root": "/",
+        });
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn requesters(activated: &ActivatedFeatures, feature: &str) -> Vec<(String, Option<String>)> {
+        activated.enabled[feature]
+            .iter()
+            .map(|requester| (requester.name.clone(), requester.via.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn only_resolved_dependencies_request_features() {
+        let activated = ActivatedFeatures::from_metadata(&metadata(), "lib").unwrap();
+        let [activated] = activated.as_slice() else {
+            panic!("expected one version of lib")
+        };
+
+        for feature in ["a", "b", "c", "d", "default", "std"] {
+            assert_eq!(
+                requesters(activated, feature),
+                [("app".into(), None)],
+                "{}",
+                feature
+            );
+        }
+        assert_eq!(
+            requesters(activated, "e"),
+            [("app".into(), Some("extra".into()))]
+        );
+        // a workspace member's dev-dependencies are part of its build
+        assert_eq!(requesters(activated, "f"), [("app".into(), None)]);
+
+        assert_eq!(activated.disabled.iter().collect::<Vec<_>>(), ["g"]);
+    }
+
+    #[test]
+    fn unknown_dependency() {
+        assert!(ActivatedFeatures::from_metadata(&metadata(), "nope").is_err());
+    }
+}
@@ -0,0 +1,438 @@
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Value};
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// A `Cargo.toml` that dependency features can be enabled and disabled in
+///
+/// This keeps the formatting and comments of the manifest
+pub struct Manifest {
+    path: PathBuf,
+    document: DocumentMut,
+}
+
+/// A dependency entry in a `Cargo.toml`
+#[derive(Debug, Clone)]
+pub struct DependencyEntry {
+    /// The table that the entry is in, e.g. `dependencies` or `target.'cfg(windows)'.dependencies`
+    pub table: String,
+    /// The name the manifest refers to the dependency as
+    pub key: String,
+    /// The name of the package
+    pub package: String,
+    /// Where the dependency comes from
+    pub source: DependencySource,
+    /// Whether the entry inherits from the workspace's dependencies (`workspace = true`)
+    pub inherited: bool,
+    /// The features that the entry enables
+    pub features: Vec<String>,
+    path: Vec<String>,
+}
+
+/// Where a dependency comes from
+#[derive(Debug, Clone)]
+pub enum DependencySource {
+    /// A version from a registry
+    Registry {
+        /// The version requirement
+        req: String,
+        /// The name of the registry, if it isn't crates.io
+        registry: Option<String>,
+    },
+    /// A local path
+    Path(PathBuf),
+    /// A git repository
+    Git(String),
+}
+
+impl Manifest {
+    /// Read the `Cargo.toml` at this path, this can be a directory or a `Cargo.toml`
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let path = if path.is_dir() {
+            path.join("Cargo.toml")
+        } else {
+            path.to_path_buf()
+        };
+
+        let data = std::fs::read_to_string(&path)
+            .map_err(|err| anyhow::anyhow!("cannot read '{}': {}", path.display(), err))?;
+        let document = data
+            .parse()
+            .map_err(|err| anyhow::anyhow!("cannot parse '{}': {}", path.display(), err))?;
+
+        Ok(Self { path, document })
+    }
+
+    /// The path to the `Cargo.toml`
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Find every entry for this dependency, by either the name it is referred to as or its package name
+    ///
+    /// This looks in the dependency tables, the `[target.*]` dependency tables and `[workspace.dependencies]`
+    pub fn find(&self, name: &str) -> anyhow::Result<Vec<DependencyEntry>> {
+        let mut tables = DEPENDENCY_TABLES
+            .iter()
+            .map(|table| vec![table.to_string()])
+            .collect::<Vec<_>>();
+
+        if let Some(targets) = self.document.get("target").and_then(Item::as_table_like) {
+            for (target, _) in targets.iter() {
+                tables.extend(DEPENDENCY_TABLES.iter().map(|table| {
+                    vec!["target".to_string(), target.to_string(), table.to_string()]
+                }));
+            }
+        }
+        tables.push(vec!["workspace".to_string(), "dependencies".to_string()]);
+
+        let mut entries = vec![];
+        for table in tables {
+            let deps = match get(self.document.as_item(), &table).and_then(Item::as_table_like) {
+                Some(deps) => deps,
+                None => continue,
+            };
+
+            for (key, item) in deps.iter() {
+                let package = item.get("package").and_then(Item::as_str).unwrap_or(key);
+                if key != name && package != name {
+                    continue;
+                }
+
+                let mut path = table.clone();
+                path.push(key.to_string());
+                entries.push(self.make_entry(path, key, package, item)?);
+            }
+        }
+
+        anyhow::ensure!(
+            !entries.is_empty(),
+            "'{}' is not a dependency in '{}'",
+            name,
+            self.path.display()
+        );
+        Ok(entries)
+    }
+
+    fn make_entry(
+        &self,
+        path: Vec<String>,
+        key: &str,
+        package: &str,
+        item: &Item,
+    ) -> anyhow::Result<DependencyEntry> {
+        let features = item
+            .get("features")
+            .and_then(Item::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|value| value.as_str().map(ToString::to_string))
+            .collect();
+
+        let inherited = item
+            .get("workspace")
+            .and_then(Item::as_bool)
+            .unwrap_or(false);
+
+        let (package, source) = if inherited {
+            self.find_inherited(key)?
+        } else {
+            let dir = self.path.parent().unwrap_or_else(|| Path::new("."));
+            (package.to_string(), find_source(key, item, dir)?)
+        };
+
+        let table = match path.as_slice() {
+            [target, name, table, _] if target == "target" => {
+                format!("target.'{}'.{}", name, table)
+            }
+            [.., _] => path[..path.len() - 1].join("."),
+            [] => unreachable!("entries always have a path"),
+        };
+
+        Ok(DependencyEntry {
+            table,
+            key: key.to_string(),
+            package,
+            source,
+            inherited,
+            features,
+            path,
+        })
+    }
+
+    /// Find the workspace dependency that an entry inherits from
+    fn find_inherited(&self, key: &str) -> anyhow::Result<(String, DependencySource)> {
+        let dir = self.path.parent().unwrap_or_else(|| Path::new("."));
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+
+        for dir in dir.ancestors() {
+            let manifest = dir.join("Cargo.toml");
+            let root = match std::fs::read_to_string(&manifest) {
+                Ok(data) => data.parse::<DocumentMut>()?,
+                Err(..) => continue,
+            };
+            if root.get("workspace").is_none() {
+                continue;
+            }
+
+            let item =
+                get(root.as_item(), &["workspace", "dependencies", key]).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "'{}' is not in the [workspace.dependencies] of '{}'",
+                        key,
+                        manifest.display()
+                    )
+                })?;
+
+            let package = item.get("package").and_then(Item::as_str).unwrap_or(key);
+            return Ok((package.to_string(), find_source(key, item, dir)?));
+        }
+
+        anyhow::bail!(
+            "cannot find the workspace that '{}' inherits '{}' from",
+            self.path.display(),
+            key
+        )
+    }
+
+    /// Enable and disable these features on the dependency entry
+    ///
+    /// The features should already have been checked against the dependency's features.
+    /// This returns whether the entry was changed
+    pub fn apply(
+        &mut self,
+        entry: &DependencyEntry,
+        enable: &[String],
+        disable: &[String],
+    ) -> bool {
+        let changed = enable.iter().any(|name| !entry.features.contains(name))
+            || disable.iter().any(|name| entry.features.contains(name));
+        if !changed {
+            return false;
+        }
+
+        let item = get_mut(self.document.as_item_mut(), &entry.path)
+            .expect("entry should exist in the manifest");
+
+        // `name = "1.0"` has to become `name = { version = "1.0" }` to have features
+        if let Some(Value::String(version)) = item.as_value() {
+            let decor = version.decor().clone();
+            let mut table = InlineTable::new();
+            table.insert("version", version.value().as_str().into());
+            table.fmt();
+            *table.decor_mut() = decor;
+            *item = Item::Value(Value::InlineTable(table));
+        }
+
+        let table = item
+            .as_table_like_mut()
+            .expect("dependency entries are strings or tables");
+
+        let inserted = table.get("features").and_then(Item::as_array).is_none();
+        if inserted {
+            table.insert("features", Item::Value(Value::Array(Array::new())));
+        }
+
+        let features = table
+            .get_mut("features")
+            .and_then(Item::as_array_mut)
+            .expect("features was inserted");
+
+        features.retain(|value| {
+            value
+                .as_str()
+                .is_none_or(|s| !disable.iter().any(|d| d == s))
+        });
+        for name in enable {
+            if !features.iter().any(|value| value.as_str() == Some(name)) {
+                features.push(name.as_str());
+            }
+        }
+
+        let removed = features.is_empty();
+        if removed {
+            table.remove("features");
+        }
+
+        // the spacing around the last key would otherwise be wrong, e.g. `{ version = "1.0"}`
+        if inserted || removed {
+            if let Some(table) = item.as_inline_table_mut() {
+                table.fmt();
+            }
+        }
+        true
+    }
+
+    /// Write the manifest back to its `Cargo.toml`
+    pub fn save(&self) -> anyhow::Result<()> {
+        std::fs::write(&self.path, self.document.to_string())
+            .map_err(|err| anyhow::anyhow!("cannot write '{}': {}", self.path.display(), err))
+    }
+}
+
+fn find_source(key: &str, item: &Item, dir: &Path) -> anyhow::Result<DependencySource> {
+    if let Some(req) = item.as_str() {
+        return Ok(DependencySource::Registry {
+            req: req.to_string(),
+            registry: None,
+        });
+    }
+
+    let field = |name| item.get(name).and_then(Item::as_str);
+    if let Some(path) = field("path") {
+        return Ok(DependencySource::Path(dir.join(path)));
+    }
+    if let Some(git) = field("git") {
+        return Ok(DependencySource::Git(git.to_string()));
+    }
+    match field("version") {
+        Some(req) => Ok(DependencySource::Registry {
+            req: req.to_string(),
+            registry: field("registry").map(ToString::to_string),
+        }),
+        None => anyhow::bail!("cannot find the version of '{}'", key),
+    }
+}
+
+fn get<'a>(mut item: &'a Item, path: &[impl AsRef<str>]) -> Option<&'a Item> {
+    for key in path {
+        item = item.as_table_like()?.get(key.as_ref())?;
+    }
+    Some(item)
+}
+
+fn get_mut<'a>(mut item: &'a mut Item, path: &[impl AsRef<str>]) -> Option<&'a mut Item> {
+    for key in path {
+        item = item.as_table_like_mut()?.get_mut(key.as_ref())?;
+    }
+    Some(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(path: &Path, data: &str) -> Manifest {
+        Manifest {
+            path: path.to_path_buf(),
+            document: data.parse().unwrap(),
+        }
+    }
+
+    fn features(list: &[&str]) -> Vec<String> {
+        list.iter().map(ToString::to_string).collect()
+    }
+
+    // enable and disable features on the only entry for this dependency, returning the new manifest
+    fn apply(data: &str, name: &str, enable: &[&str], disable: &[&str]) -> (bool, String) {
+        let mut manifest = manifest(Path::new("/crate/Cargo.toml"), data);
+        let [entry] = &manifest.find(name).unwrap()[..] else {
+            panic!("expected one entry for {}", name)
+        };
+        let changed = manifest.apply(entry, &features(enable), &features(disable));
+        (changed, manifest.document.to_string())
+    }
+
+    #[test]
+    fn version_string_becomes_a_table() {
+        let data = "[dependencies]\nserde = \"1\" # serialization\nlog = \"0.4\"\n";
+        let (changed, output) = apply(data, "serde", &["derive", "rc"], &[]);
+        assert!(changed);
+        assert_eq!(
+            output,
+            "[dependencies]\nserde = { version = \"1\", features = [\"derive\", \"rc\"] } # serialization\nlog = \"0.4\"\n"
+        );
+    }
+
+    #[test]
+    fn target_tables() {
+        let data = "[target.'cfg(windows)'.dependencies]\nwinapi = { version = \"0.3\", features = [\"std\"] }\n";
+
+        let manifest = manifest(Path::new("/crate/Cargo.toml"), data);
+        let entries = manifest.find("winapi").unwrap();
+        assert_eq!(entries[0].table, "target.'cfg(windows)'.dependencies");
+        assert_eq!(entries[0].features, ["std"]);
+
+        let (changed, output) = apply(data, "winapi", &["winuser"], &[]);
+        assert!(changed);
+        assert_eq!(
+            output,
+            "[target.'cfg(windows)'.dependencies]\nwinapi = { version = \"0.3\", features = [\"std\", \"winuser\"] }\n"
+        );
+    }
+
+    #[test]
+    fn renamed_dependencies() {
+        let data = "[dependencies]\njson = { package = \"serde_json\", version = \"1\" }\n";
+
+        let manifest = manifest(Path::new("/crate/Cargo.toml"), data);
+        let entries = manifest.find("serde_json").unwrap();
+        assert_eq!(entries[0].key, "json");
+        assert_eq!(entries[0].package, "serde_json");
+        assert!(manifest.find("json").is_ok());
+
+        let (changed, output) = apply(data, "serde_json", &["raw_value"], &[]);
+        assert!(changed);
+        assert_eq!(
+            output,
+            "[dependencies]\njson = { package = \"serde_json\", version = \"1\", features = [\"raw_value\"] }\n"
+        );
+    }
+
+    #[test]
+    fn inherited_dependencies() {
+        let root = std::env::temp_dir().join(format!("whatfeatures-apply-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"member\"]\n\n[workspace.dependencies]\nserde_json = { version = \"1.0.100\", package = \"serde_json\" }\n",
+        )
+        .unwrap();
+
+        let data = "[dependencies]\nserde_json = { workspace = true }\n";
+        let mut manifest = manifest(&root.join("member").join("Cargo.toml"), data);
+        let entries = manifest.find("serde_json");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let entries = entries.unwrap();
+        assert!(entries[0].inherited);
+        assert!(matches!(
+            &entries[0].source,
+            DependencySource::Registry { req, registry: None } if req == "1.0.100"
+        ));
+
+        assert!(manifest.apply(&entries[0], &features(&["std"]), &[]));
+        assert_eq!(
+            manifest.document.to_string(),
+            "[dependencies]\nserde_json = { workspace = true, features = [\"std\"] }\n"
+        );
+    }
+
+    #[test]
+    fn empty_features_are_removed() {
+        let data = "[dependencies]\nserde = { version = \"1\", features = [\"derive\"] }\n";
+        let (changed, output) = apply(data, "serde", &[], &["derive"]);
+        assert!(changed);
+        assert_eq!(output, "[dependencies]\nserde = { version = \"1\" }\n");
+
+        let data = "[dependencies.serde]\nversion = \"1\"\nfeatures = [\"derive\", \"rc\"]\n";
+        let (changed, output) = apply(data, "serde", &[], &["derive", "rc"]);
+        assert!(changed);
+        assert_eq!(output, "[dependencies.serde]\nversion = \"1\"\n");
+    }
+
+    #[test]
+    fn unchanged() {
+        let data =
+            "[dependencies]\nserde = { version = \"1\", features = [\"derive\"] }\nlog = \"0.4\"\n";
+        assert_eq!(
+            apply(data, "serde", &[], &["rc"]),
+            (false, data.to_string())
+        );
+        assert_eq!(
+            apply(data, "serde", &["derive"], &[]),
+            (false, data.to_string())
+        );
+        assert_eq!(apply(data, "log", &[], &["std"]), (false, data.to_string()));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{printer::YankStatus, Theme};
+use crate::{
+    printer::{Format, YankStatus},
+    Theme,
+};
 use pico_args::Arguments;
 use std::path::{Path, PathBuf};
 
@@ -12,6 +15,15 @@ pub enum Error {
         provided_long: String,
     },
 
+    FlagRequiresLocal {
+        provided: String,
+    },
+
+    FlagRequiresFlag {
+        provided: String,
+        required: String,
+    },
+
     NameRequired,
 
     Exclusive {
@@ -40,6 +52,34 @@ pub enum Error {
         option: String,
         allowed: Vec<&'static str>,
     },
+
+    RegistryMismatch {
+        pkgid: String,
+        flag: String,
+    },
+
+    InvalidVersionReq {
+        semver: String,
+        reason: String,
+    },
+
+    EmptyRegistry {
+        pkgid: String,
+    },
+
+    VersionAfterAt {
+        name: String,
+        semver: String,
+    },
+
+    InvalidDiff {
+        spec: String,
+        reason: &'static str,
+    },
+
+    InvalidRustVersion {
+        version: String,
+    },
 }
 
 impl Error {
@@ -108,6 +148,14 @@ impl std::fmt::Display for Error {
                 )?;
             }
 
+            Self::FlagRequiresLocal { provided } => {
+                write!(f, "flag [{}] requires that the crate be local", provided)?;
+            }
+
+            Self::FlagRequiresFlag { provided, required } => {
+                write!(f, "flag [{}] must be used with [{}]", provided, required)?;
+            }
+
             Self::NameRequired => {
                 write!(f, "A package name must be supplied")?;
             }
@@ -174,6 +222,38 @@ impl std::fmt::Display for Error {
                 )?;
             }
 
+            Self::InvalidVersionReq { semver, reason } => {
+                write!(f, "invalid version requirement '{}': {}", semver, reason)?;
+            }
+
+            Self::EmptyRegistry { pkgid } => {
+                write!(
+                    f,
+                    "pkgid '{}' has an empty registry. use `name@registry`, or leave off the `@`",
+                    pkgid
+                )?;
+            }
+
+            Self::VersionAfterAt { name, semver } => {
+                write!(
+                    f,
+                    "`@` is for the registry, not the version. use `{}:{}` instead",
+                    name, semver
+                )?;
+            }
+
+            Self::InvalidDiff { spec, reason } => {
+                write!(f, "invalid `--diff` '{}': {}", spec, reason)?;
+            }
+
+            Self::InvalidRustVersion { version } => {
+                write!(
+                    f,
+                    "invalid `--rust-version` '{}': expected a rust version, e.g. 1.70 or 1.70.0",
+                    version
+                )?;
+            }
+
             Self::UnknownOption { option, allowed } => {
                 let options =
                     allowed
@@ -193,6 +273,14 @@ impl std::fmt::Display for Error {
                     option, options
                 )?;
             }
+
+            Self::RegistryMismatch { pkgid, flag } => {
+                write!(
+                    f,
+                    "the pkgid uses the registry '{}' but `--registry` was '{}'",
+                    pkgid, flag
+                )?;
+            }
         };
 
         Ok(())
@@ -257,7 +345,9 @@ pub enum PkgId {
         name: String,
         /// Specified semver
         semver: Option<String>,
-    }, // TODO supports more registries than just crates.io
+        /// Specified registry, from the `[registries]` table of the cargo config
+        registry: Option<String>,
+    },
     /// Local directory or file
     Local(PathBuf),
 }
@@ -275,16 +365,31 @@ impl PkgId {
     pub fn is_local(&self) -> bool {
         matches!(self, Self::Local { .. })
     }
+
+    /// The registry specified for this package, if any
+    pub fn registry(&self) -> Option<&str> {
+        match &self {
+            Self::Remote { registry, .. } => registry.as_deref(),
+            Self::Local(..) => None,
+        }
+    }
 }
 
 impl std::fmt::Display for PkgId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
-            Self::Remote { name, semver } => {
+            Self::Remote {
+                name,
+                semver,
+                registry,
+            } => {
                 write!(f, "{}", name)?;
                 if let Some(ver) = &semver {
                     write!(f, ":{}", ver)?;
                 }
+                if let Some(registry) = &registry {
+                    write!(f, "@{}", registry)?;
+                }
                 Ok(())
             }
             Self::Local(l) => write!(f, "{}", l.display()),
@@ -300,10 +405,36 @@ impl std::str::FromStr for PkgId {
             return Err(Error::PkgIdIsLocal);
         }
 
+        let (input, registry) = match input.rsplit_once('@') {
+            Some(..) if input.ends_with('@') => {
+                return Err(Error::EmptyRegistry {
+                    pkgid: input.to_string(),
+                })
+            }
+            // cargo's `name@version` form
+            Some((name, semver)) if semver::VersionReq::parse(semver).is_ok() => {
+                return Err(Error::VersionAfterAt {
+                    name: name.to_string(),
+                    semver: semver.to_string(),
+                })
+            }
+            Some((input, registry)) => (input, Some(registry.to_string())),
+            None => (input, None),
+        };
+
         let mut iter = input.splitn(2, ':');
         let name = iter.next().ok_or_else(|| Error::NameRequired)?;
         let semver = iter.next().map(ToString::to_string);
 
+        if let Some(semver) = &semver {
+            if let Err(err) = semver::VersionReq::parse(semver) {
+                return Err(Error::InvalidVersionReq {
+                    semver: semver.clone(),
+                    reason: err.to_string(),
+                });
+            }
+        }
+
         let path = Path::new(name);
         if path.is_dir() || path.is_file() {
             return Err(Error::PkgIdIsLocal);
@@ -312,10 +443,19 @@ impl std::str::FromStr for PkgId {
         Ok(Self::Remote {
             name: name.to_string(),
             semver,
+            registry,
         })
     }
 }
 
+/// Whether a crate argument is a path, rather than a pkgid (e.g. `.`, `../foo` or `foo/Cargo.toml`)
+fn looks_like_path(input: &str) -> bool {
+    input.starts_with('.')
+        || input.contains(['/', '\\'])
+        || input.ends_with("Cargo.toml")
+        || Path::new(input).exists()
+}
+
 /// Input for the program
 #[derive(Debug)]
 pub struct Args {
@@ -354,9 +494,68 @@ pub struct Args {
 
     /// Output json instead of human readable
     pub json: bool,
+
+    /// The format to output, `json` is set when this is `Format::Json`
+    pub format: Format,
+
+    /// The registry to use, rather than crates.io
+    pub registry: Option<String>,
+
+    /// Should pre-releases be considered for the latest version?
+    pub pre_release: bool,
+
+    /// The features to resolve, rather than listing all of them
+    pub enable: Option<Vec<String>>,
+
+    /// The feature or optional dependency to find the enabling features for
+    pub why: Option<String>,
+
+    /// The old and new versions to compare
+    pub diff: Option<(String, String)>,
+
+    /// Should we show when each feature was added and removed?
+    pub history: bool,
+
+    /// The dependency to show the enabled features of, in the local workspace's build
+    pub resolved: Option<String>,
+
+    /// The target triple to filter target-specific dependencies with
+    pub target: Option<String>,
+
+    /// Should target-specific dependencies for other targets be shown (greyed out)?
+    pub other_targets: bool,
+
+    /// Should a `Cargo.toml` dependency line be printed, with the `enable` features?
+    pub toml: bool,
+
+    /// Should the dependency line disable the default features?
+    pub no_default_features: bool,
+
+    /// The dependency to enable (and disable) features on, in the local `Cargo.toml`
+    pub apply: Option<String>,
+
+    /// The features to disable, with `apply`
+    pub disable: Option<Vec<String>>,
+
+    /// Should the source be scanned for where each feature is used?
+    pub scan: bool,
+
+    /// Only list the versions that build with this rust version
+    pub rust_version: Option<semver::Version>,
+
+    /// Only list the versions that have this feature
+    pub requires_feature: Option<String>,
 }
 
 impl Args {
+    /// Resolve the registry to use from the cargo config, this defaults to crates.io
+    pub fn registry(&self) -> anyhow::Result<crate::RegistryConfig> {
+        match self.registry.as_deref() {
+            Some(name) => crate::RegistryConfig::from_cargo_config(name),
+            None => Ok(crate::RegistryConfig::crates_io()),
+        }
+    }
+
     fn try_parse_help(args: &mut Arguments) -> anyhow::Result<()> {
         if args.contains(["-V", "--version"]) {
             print_version()
@@ -386,8 +585,13 @@ impl Args {
             std::process::exit(0);
         }
 
+        if args.contains("--json-schema") {
+            println!("{}", crate::json::JSON_SCHEMA.trim_end());
+            std::process::exit(0);
+        }
+
         if args.contains("--purge") {
-            let total = crate::Registry::from_local()?.purge_local_cache()?;
+            let total = crate::Registry::purge_all_caches()?;
             println!(
                 "purged {} crates from {}",
                 total,
@@ -396,6 +600,36 @@ impl Args {
             std::process::exit(0)
         }
 
+        if args.contains("--cache-info") {
+            print_cache_info()?;
+            std::process::exit(0)
+        }
+
+        if args.contains("--gc") {
+            let limits = crate::CacheLimits::from_env()?;
+            if limits.is_unlimited() {
+                anyhow::bail!(
+                    "no cache limits are set. set {} or {}, or add a [cache] table to {}",
+                    crate::cache::MAX_SIZE_ENV,
+                    crate::cache::MAX_AGE_ENV,
+                    crate::cache::config_file()?.display()
+                )
+            }
+
+            let mut cache = crate::Cache::open()?;
+            let crate::Collected { removed, failed } = cache.collect_garbage(&limits)?;
+            println!(
+                "removed {} crates ({}) from {}",
+                removed.len(),
+                crate::cache::format_size(removed.iter().map(|entry| entry.size).sum()),
+                cache.root().display()
+            );
+            for (_, err) in &failed {
+                eprintln!("{:#}", err);
+            }
+            std::process::exit(if failed.is_empty() { 0 } else { 1 })
+        }
+
         Ok(())
     }
 
@@ -412,6 +646,29 @@ impl Args {
         .map_err(Into::into)
     }
 
+    fn try_parse_format(args: &mut Arguments, json: bool) -> anyhow::Result<Format> {
+        let format = args.opt_value_from_fn("--format", |s| match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "dot" => Ok(Format::Dot),
+            "mermaid" => Ok(Format::Mermaid),
+            s => Err(Error::UnknownOption {
+                option: s.to_string(),
+                allowed: vec!["text", "json", "markdown", "dot", "mermaid"],
+            }),
+        })?;
+
+        match (json, format) {
+            (true, None) | (true, Some(Format::Json)) => Ok(Format::Json),
+            (true, Some(..)) => anyhow::bail!(Error::exclusive(vec![
+                vec!["-j", "--json"],
+                vec!["--format"]
+            ])),
+            (false, format) => Ok(format.unwrap_or_default()),
+        }
+    }
+
     fn try_parse_color(args: &mut Arguments) -> anyhow::Result<()> {
         let color: Option<Color> = args.opt_value_from_str(["-c", "--color"])?;
 
@@ -454,9 +711,32 @@ impl Args {
             name_only,
             pkgid,
             show_yanked,
+            pre_release,
+            enable,
+            why,
+            diff,
+            history,
+            resolved,
+            target,
+            other_targets,
+            format,
+            toml,
+            no_default_features,
+            apply,
+            disable,
+            scan,
+            rust_version,
+            requires_feature,
             ..
         } = &this;
 
+        // the graphs are only of the features
+        let graph_format = match format {
+            Format::Dot => Some("--format dot"),
+            Format::Mermaid => Some("--format mermaid"),
+            _ => None,
+        };
+
         /*
         list is exclusive with:
             no_features
@@ -482,6 +762,36 @@ impl Args {
             if *show_private {
                 bad.push(vec!["-r", "--restricted"]);
             }
+            if enable.is_some() {
+                bad.push(vec!["--enable"]);
+            }
+            if why.is_some() {
+                bad.push(vec!["--why"]);
+            }
+            if diff.is_some() {
+                bad.push(vec!["--diff"]);
+            }
+            if *history {
+                bad.push(vec!["-H", "--history"]);
+            }
+            if resolved.is_some() {
+                bad.push(vec!["--resolved"]);
+            }
+            if target.is_some() {
+                bad.push(vec!["--target"]);
+            }
+            if let Some(format) = graph_format {
+                bad.push(vec![format]);
+            }
+            if *toml {
+                bad.push(vec!["--toml"]);
+            }
+            if apply.is_some() {
+                bad.push(vec!["--apply"]);
+            }
+            if *scan {
+                bad.push(vec!["--scan"]);
+            }
             if pkgid.is_local() {
                 bad.push(vec!["<crate>"]);
             }
@@ -497,17 +807,164 @@ impl Args {
             });
         }
 
+        if *pre_release && pkgid.is_local() {
+            anyhow::bail!(Error::FlagRequiresRemote {
+                provided_short: "-P".into(),
+                provided_long: "--pre-release".into(),
+            });
+        }
+
         if *name_only {
             let mut bad = vec![];
             if *show_deps {
                 bad.push(vec!["-d", "--deps"]);
             }
+            if enable.is_some() {
+                bad.push(vec!["--enable"]);
+            }
+            if why.is_some() {
+                bad.push(vec!["--why"]);
+            }
+            if diff.is_some() {
+                bad.push(vec!["--diff"]);
+            }
+            if *history {
+                bad.push(vec!["-H", "--history"]);
+            }
+            if resolved.is_some() {
+                bad.push(vec!["--resolved"]);
+            }
+            if target.is_some() {
+                bad.push(vec!["--target"]);
+            }
+            if let Some(format) = graph_format {
+                bad.push(vec![format]);
+            }
+            if *toml {
+                bad.push(vec!["--toml"]);
+            }
+            if apply.is_some() {
+                bad.push(vec!["--apply"]);
+            }
+            if *scan {
+                bad.push(vec!["--scan"]);
+            }
 
             if !bad.is_empty() {
                 anyhow::bail!(Error::exclusive_with(bad, "-s", "--short"))
             }
         }
 
+        if rust_version.is_some() && !*list {
+            anyhow::bail!(Error::FlagRequiresFlag {
+                provided: "--rust-version".into(),
+                required: "--list".into(),
+            })
+        }
+
+        if requires_feature.is_some() && !*list {
+            anyhow::bail!(Error::FlagRequiresFlag {
+                provided: "--requires-feature".into(),
+                required: "--list".into(),
+            })
+        }
+
+        if *no_default_features && !*toml {
+            anyhow::bail!(Error::FlagRequiresFlag {
+                provided: "--no-default-features".into(),
+                required: "--toml".into(),
+            })
+        }
+
+        if disable.is_some() && apply.is_none() {
+            anyhow::bail!(Error::FlagRequiresFlag {
+                provided: "--disable".into(),
+                required: "--apply".into(),
+            })
+        }
+
+        if apply.is_some() && enable.is_none() && disable.is_none() {
+            anyhow::bail!(Error::FlagRequiresFlag {
+                provided: "--apply".into(),
+                required: "--enable or --disable".into(),
+            })
+        }
+
+        // with --toml and --apply, --enable is the features for the dependency
+        let modes = [
+            (enable.is_some() && !*toml && apply.is_none(), "--enable"),
+            (*toml, "--toml"),
+            (apply.is_some(), "--apply"),
+            (*scan, "--scan"),
+            (why.is_some(), "--why"),
+            (diff.is_some(), "--diff"),
+            (*history, "--history"),
+            (resolved.is_some(), "--resolved"),
+        ];
+        let modes = modes
+            .iter()
+            .filter_map(|&(used, flag)| if used { Some(vec![flag]) } else { None })
+            .collect::<Vec<_>>();
+        if modes.len() > 1 {
+            anyhow::bail!(Error::exclusive(modes))
+        }
+
+        if let (Format::Json, Some(mode)) = (format, modes.first()) {
+            if *toml || apply.is_some() {
+                anyhow::bail!(Error::exclusive(vec![vec!["-j", "--json"], mode.clone()]))
+            }
+        }
+
+        // markdown is only available for the listings, and the features and dependencies
+        let format = match format {
+            Format::Markdown => Some("--format markdown"),
+            _ => graph_format,
+        };
+        if let (Some(format), Some(mode)) = (format, modes.first()) {
+            anyhow::bail!(Error::exclusive(vec![vec![format], mode.clone()]))
+        }
+
+        if *history && pkgid.is_local() {
+            anyhow::bail!(Error::FlagRequiresRemote {
+                provided_short: "-H".into(),
+                provided_long: "--history".into(),
+            });
+        }
+
+        if *other_targets && target.is_none() {
+            anyhow::bail!(Error::inclusive_with(
+                vec![vec!["--target"]],
+                "-T",
+                "--other-targets"
+            ))
+        }
+
+        if resolved.is_some() && !pkgid.is_local() {
+            anyhow::bail!(Error::FlagRequiresLocal {
+                provided: "--resolved".into(),
+            });
+        }
+
+        if apply.is_some() && !pkgid.is_local() {
+            anyhow::bail!(Error::FlagRequiresLocal {
+                provided: "--apply".into(),
+            });
+        }
+
+        if let (
+            Some((old, new)),
+            PkgId::Local(..)
+            | PkgId::Remote {
+                semver: Some(..), ..
+            },
+        ) = (diff, pkgid)
+        {
+            anyhow::bail!(Error::InvalidDiff {
+                spec: format!("{}..{}", old, new),
+                reason: "it requires a remote crate name, without a version",
+            })
+        }
+
         if *no_features && (!show_deps && !*name_only) {
             anyhow::bail!(Error::inclusive_with(
                 vec![vec!["-d", "--deps"], vec!["-s", "--short"]],
@@ -529,7 +986,11 @@ impl Args {
 
     /// Parse the arguments
     pub fn parse() -> anyhow::Result<Self> {
-        let mut args = pico_args::Arguments::from_env();
+        Self::parse_from(std::env::args_os().skip(1).collect())
+    }
+
+    fn parse_from(args: Vec<std::ffi::OsString>) -> anyhow::Result<Self> {
+        let mut args = pico_args::Arguments::from_vec(args);
 
         Self::try_parse_help(&mut args)?;
         Self::try_parse_cache(&mut args)?;
@@ -546,6 +1007,14 @@ impl Args {
         let verbose = args.contains(["-v", "--verbose"]);
         let local_only = args.contains(["-t", "--this-crate"]);
         let json = args.contains(["-j", "--json"]);
+        let format = Self::try_parse_format(&mut args, json)?;
+        let json = format == Format::Json;
+        let pre_release = args.contains(["-P", "--pre-release"]);
+        let history = args.contains(["-H", "--history"]);
+        let other_targets = args.contains(["-T", "--other-targets"]);
+        let toml = args.contains("--toml");
+        let no_default_features = args.contains("--no-default-features");
+        let scan = args.contains("--scan");
 
         let mut theme = Self::try_parse_theme(&mut args)?;
 
@@ -557,6 +1026,39 @@ impl Args {
             theme = override_theme?
         }
 
+        let mut registry: Option<String> = args.opt_value_from_str("--registry")?;
+
+        // this is the same syntax as cargo's `--features`
+        let features = |s: &str| {
+            Ok::<_, std::convert::Infallible>(
+                s.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|s| !s.is_empty())
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let enable = args.opt_value_from_fn("--enable", features)?;
+        let disable = args.opt_value_from_fn("--disable", features)?;
+        let why: Option<String> = args.opt_value_from_str("--why")?;
+        let resolved: Option<String> = args.opt_value_from_str("--resolved")?;
+        let apply: Option<String> = args.opt_value_from_str("--apply")?;
+        let target: Option<String> = args.opt_value_from_str("--target")?;
+        let rust_version = args.opt_value_from_fn("--rust-version", |s| {
+            crate::util::parse_rust_version(s).ok_or_else(|| Error::InvalidRustVersion {
+                version: s.to_string(),
+            })
+        })?;
+        let requires_feature: Option<String> = args.opt_value_from_str("--requires-feature")?;
+        let diff = args.opt_value_from_fn("--diff", |s| match s.split_once("..") {
+            Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+                Ok((old.to_string(), new.to_string()))
+            }
+            _ => Err(Error::InvalidDiff {
+                spec: s.to_string(),
+                reason: "expected <old>..<new>, e.g. 1.0.0..1.2.0",
+            }),
+        })?;
+
         let manifest_path: Option<PathBuf> = args.opt_value_from_str("--manifest-path")?;
         let mut pkgid: Option<PkgId> = args.opt_value_from_str(["-p", "--pkgid"])?;
 
@@ -581,17 +1083,23 @@ impl Args {
             0 if manifest_path.is_some() => {
                 pkgid.replace(PkgId::Local(manifest_path.unwrap()));
             }
+            // the workspace being built is the current directory, unless one is provided
+            0 if resolved.is_some() || apply.is_some() => {
+                pkgid.replace(PkgId::Local(PathBuf::from(".")));
+            }
             0 => anyhow::bail!(Error::NoCrateName),
             n if n > 0 && pkgid.is_some() => anyhow::bail!(Error::exclusive(vec![
                 vec!["-p", "--pkgid"],
                 vec!["<crate>"]
             ])),
             1 => {
-                // TODO make this determine if its a remote or local package (prefer remote)
+                // a remote crate is preferred, unless this is clearly a path
                 let name = crate_names.remove(0);
                 let p = match name.parse() {
+                    _ if looks_like_path(&name) => PkgId::Local(PathBuf::from(name)),
                     Ok(pkgid) => pkgid,
-                    Err(..) => PkgId::Local(PathBuf::from(name)),
+                    Err(Error::PkgIdIsLocal) => PkgId::Local(PathBuf::from(name)),
+                    Err(err) => anyhow::bail!(err),
                 };
                 pkgid.replace(p);
             }
@@ -602,6 +1110,22 @@ impl Args {
             anyhow::bail!(Error::NoCrateOrPkgId)
         }
 
+        match (
+            pkgid.as_ref().and_then(PkgId::registry),
+            registry.as_deref(),
+        ) {
+            (Some(left), Some(right)) if left != right => {
+                anyhow::bail!(Error::RegistryMismatch {
+                    pkgid: left.to_string(),
+                    flag: right.to_string(),
+                })
+            }
+            (Some(left), None) => {
+                registry.replace(left.to_string());
+            }
+            _ => {}
+        }
+
         Self::verify_flags(Self {
             verbose,
 
@@ -621,6 +1145,26 @@ impl Args {
             theme,
 
             json,
+            format,
+
+            registry,
+            pre_release,
+
+            enable,
+            why,
+            diff,
+            history,
+            resolved,
+            target,
+            other_targets,
+            toml,
+            no_default_features,
+            apply,
+            disable,
+            scan,
+
+            rust_version,
+            requires_feature,
         })
     }
 }
@@ -660,14 +1204,35 @@ FLAGS:
     -v, --verbose               Print all leaf nodes and optional deps
     -o, --offline               Don't connect to the internet, limits the availities of this.
     -j, --json                  Prints json rather than a human-readable format
+    -P, --pre-release           Consider pre-release versions when finding the latest version
+    -H, --history               Show the version each feature was added, removed or made default in
+    -T, --other-targets         With --target, also show the dependencies for other targets, greyed out
     --print-cache-dir           Prints out the path to the cache directory
     --purge                     Purges the local cache
+    --gc                        Removes crates from the local cache that are over the cache limits
+    --cache-info                Prints the size of the local cache, its limits and the least recently used crates
+    --json-schema               Prints the JSON Schema for the json output
+    --toml                      Prints a Cargo.toml dependency line, with the --enable features
+    --scan                      Scans the source of the crate for where each feature is used
+    --no-default-features       Disable the default features in the --toml dependency line
     --theme                     Use a different theme
 
 OPTIONS:
     -c, --color <WHEN>          Attempts to use colors when printing as text [default: auto]
-    -p, --pkgid <SPEC>          A `pkgid` spec. e.g. cargo:1.43.0
+    -p, --pkgid <SPEC>          A `pkgid` spec. e.g. cargo:1.43.0, serde:1 or foo:0.1.0@my-registry
     --manifest-path <PATH>      A path to the Cargo.toml you want to read, locally.
+    --registry <NAME>           A registry from the cargo config to use, rather than crates.io
+    --format <FORMAT>           The output format: text, json, markdown, dot or mermaid [default: text]
+    --enable <FEATURES>         Show everything these features enable. e.g. --enable default,derive
+    --why <NAME>                Show which features enable this feature or optional dependency
+    --diff <OLD>..<NEW>         Show what changed in the features and dependencies between two versions
+    --resolved <CRATE>          Show which features of this dependency are enabled in a local workspace's build
+    --target <TRIPLE>           Only show the target-specific dependencies that apply to this target
+    --apply <CRATE>             Enable the --enable features and disable the --disable features on a dependency
+                                in the local Cargo.toml
+    --disable <FEATURES>        The features to disable with --apply
+    --rust-version <VERSION>    With --list, only list the versions that build with this rust version
+    --requires-feature <NAME>   With --list, only list the versions that have this feature
     -y, --show-yanked <yanked>  Shows any yanked versions when using `--list`. [default: exclude].
 
 ARGS:
@@ -677,6 +1242,8 @@ ARGS:
 
 CONFIG:
     WHATFEATURES_THEME          [colorful, basic, palette, none]
+    WHATFEATURES_CACHE_MAX_SIZE The maximum size of the local cache, e.g. 500M or 2G
+    WHATFEATURES_CACHE_MAX_AGE  How long a crate can go unused in the local cache, e.g. 12h, 30d or 4w
 "#;
 
     static LONG_HELP: &str = r#"the `whatfeatures` command
@@ -709,6 +1276,7 @@ CONFIG:
         -l, --list
             List all versions for the crate.
             When using the `-y` option, yanked crates can be filtered.
+            With -v, --verbose the rust-version each version declares is shown as well.
 
         -s, --short
             Display only the name and latest version, such as foo = 0.1.2
@@ -716,15 +1284,38 @@ CONFIG:
         -v, --verbose
             When this is enabled, all 'implied' features will be listed.
             Also, optional dependencies will be listed. Optional deps are technically features.
+            Features documented with '## ' comments in the Cargo.toml show the first line of their
+            documentation, with this enabled the whole of it is shown.
+            The package's metadata (description, rust-version, edition, license, links, etc) is
+            also shown. A crate whose rust-version is newer than the rustc on the PATH is always
+            noted, even without this.
 
         -o, --offline
             Don't connect to the internet, limits the availities of this.
             If the crate is in either cargo's local registry, or whatfeatures' cache
             then this will work normally, otherwise it'll give you a nice error.
+            Versions, yank status and features can also be read from cargo's index cache
+            for any crate that cargo has resolved before.
 
         -j, --json
             This outputs JSON rather than the human readable format
 
+        -P, --pre-release
+            Consider pre-release versions (e.g. 1.0.0-beta.1) when finding the latest version.
+            Normally these are skipped, unless the crate has no other versions.
+            This has no effect when a specific version is requested.
+
+        -H, --history
+            Show when each feature was added, removed or started (or stopped) being enabled by default,
+            across every published version of the crate. This is read from the index, so nothing is downloaded.
+            Yanked versions are skipped, as are pre-releases unless -P, --pre-release is used.
+            A version requirement can be used to limit the versions, e.g. cargo whatfeatures tokio:1 --history
+            An exact version, e.g. tokio:1.0.0, only shows that version.
+
+        -T, --other-targets
+            When used with --target, the dependencies for other targets are also shown, but greyed out
+            and labelled with the target they don't apply to.
+
         --print-cache-dir
             Prints out the path to the cache directory
 
@@ -739,6 +1330,41 @@ CONFIG:
             * Windows: %LOCALAPPDATA/museun/whatfeatures
             * macOS: $HOME/Library/Caches/museun/whatfeatures
 
+        --gc
+            Removes crates from the local cache that have gone unused for longer than the max age, and then the
+            least recently used crates until the cache fits in the max size. This is also done whenever a crate
+            is added to the cache. See WHATFEATURES_CACHE_MAX_SIZE and WHATFEATURES_CACHE_MAX_AGE below.
+
+        --cache-info
+            Prints how many crates are in the local cache, how much space they use, the cache limits and
+            the least recently used crates.
+
+        --toml
+            Prints a dependency line for a Cargo.toml, with the features from --enable, e.g.
+            cargo whatfeatures serde --toml --enable derive,rc
+            prints serde = { version = "1.0.200", features = ["derive", "rc"] }
+            Each feature must exist on the crate, and a warning is printed for any feature that is already
+            enabled by another one (or by the default features).
+
+        --no-default-features
+            When used with --toml, the dependency line disables the default features
+            (default-features = false). Use --enable default to keep them.
+
+        --scan
+            Scans the src/ directory of the crate for #[cfg(..)], #![cfg(..)], #[cfg_attr(..)] and cfg!(..)
+            that use a feature, and shows how many places (and lines) each feature gates in each file.
+            With -v, --verbose every place is shown, along with the item it gates (e.g. mod de) and
+            whether it is only used when the feature is disabled (with not(..)).
+            This also lists the features that are used in the source but aren't declared, and the
+            features that are declared but that nothing uses (ignoring features that only enable
+            optional dependencies, dependency features or other used features).
+
+        --json-schema
+            Prints the JSON Schema for everything that -j, --json prints.
+            Every json document has a `schema_version` (currently 1), which changes whenever the output
+            changes in a way that isn't backwards compatible, and a `kind` that says which document it is.
+            Timestamps are RFC 3339.
+
         --theme [colorful, basic, palette, none]
             use this provided theme
 
@@ -749,7 +1375,76 @@ CONFIG:
 
         -p, --pkgid <semver>
             A specific version to lookup. e.g. foo:0.7.1
+            This can also be a version requirement, like cargo uses. e.g. foo:1, foo:~1.2 or "foo:>=0.5, <0.7"
+            The newest non-yanked version matching the requirement is used.
             If this is not provided, then the latest crate is used.
+            A registry can be provided with @, e.g. foo:0.7.1@my-registry
+
+        --registry <NAME>
+            The name of a registry to use, rather than crates.io.
+            This is looked up in the [registries] table of cargo's config files,
+            or from the CARGO_REGISTRIES_<NAME>_INDEX environment variable.
+            Only sparse registries are supported.
+            If the registry requires authentication, the token is found the same way cargo does:
+            CARGO_REGISTRIES_<NAME>_TOKEN, credentials.toml or the configured credential-provider
+
+        --format [text, json, markdown, dot, mermaid]
+            The format to print in [default: text]. `--format json` is the same as -j, --json
+            markdown prints a heading for each crate, a table of the features (with the default features
+            and what each feature enables) and tables of the dependencies for each kind of dependency.
+            This is meant to be pasted into docs or pull requests. It can be used for the features,
+            dependencies and version listings, but not with --enable, --why, --diff, --history or --resolved.
+            dot (Graphviz) and mermaid print the features as a graph, with an edge from each feature to
+            the features, optional dependencies (dep:name) and dependency features (dep/feature) that it enables.
+            Weak dependency features (dep?/feature) are dashed, and everything enabled by default is highlighted.
+            e.g. cargo whatfeatures serde --format dot | dot -Tsvg > serde.svg
+
+        --enable <FEATURES>
+            Resolve these features the way cargo would, and show everything that they enable,
+            rather than listing all of the features.
+            This is a comma or space separated list, like cargo's --features. e.g. --enable "default serde/std"
+            The default features are only included if `default` is provided.
+            This prints every enabled feature, the optional dependencies that are activated
+            and the features enabled on the dependencies.
+
+        --why <NAME>
+            Show every feature that enables this feature or optional dependency, e.g. --why openssl
+            This is printed as a tree of what enables it, and what enables that, and so on.
+            With --json, each path from a top-level feature (e.g. default) down to it is also listed.
+
+        --diff <OLD>..<NEW>
+            Compare two versions of the crate, e.g. cargo whatfeatures serde --diff 1.0.100..1.0.200
+            Either version can be a requirement, e.g. --diff 0.7..1
+            This shows the features that were added, removed or renamed, changes to the default features,
+            changes to what each feature enables and the dependencies that were added, removed or re-versioned.
+
+        --resolved <CRATE>
+            Resolve a local workspace the way cargo would build it, and show which features of this dependency
+            are enabled, e.g. cargo whatfeatures --resolved tokio
+            Each enabled feature lists the packages whose Cargo.toml requested it, and the feature of that package
+            that requested it, if it wasn't requested on the dependency itself. The features that are off are also listed.
+            Only the dependencies that apply to the host are used, or to the --target if one is provided.
+            If no crate is provided, the current directory is used. This runs `cargo metadata`, which may update
+            the lock file, unless -o, --offline is used.
+
+        --apply <CRATE>
+            Edit the dependency in the local Cargo.toml (the current directory, unless one is provided)
+            to enable the --enable features and disable the --disable features, e.g.
+            cargo whatfeatures --apply tokio --enable macros,rt --disable full
+            Every entry for the dependency is edited, including the [target.*] tables and [workspace.dependencies].
+            Entries that are inherited with `workspace = true` have their own features edited.
+            Formatting and comments are kept. Each feature must exist on the version of the dependency that
+            the entry requires, otherwise nothing is changed.
+
+        --disable <FEATURES>
+            The features to remove from the dependency, with --apply.
+            This is a comma or space separated list, like --enable
+
+        --target <TRIPLE>
+            Only show the target-specific dependencies (e.g. [target.'cfg(windows)'.dependencies]) that apply
+            to this target triple, e.g. --target x86_64-pc-windows-msvc
+            The cfg values for the target are read from `rustc --print cfg --target <TRIPLE>`, so the target
+            must be known to rustc (but doesn't need to be installed). The RUSTC environment variable is used if set.
 
         --manifest-path <PATH>
             A path to the Cargo.toml you want to read, locally.
@@ -762,6 +1457,13 @@ CONFIG:
             When 'include' is provided, the listing will include yanked versions along with active releases.
             When 'only' is provided, only yanked versions will be listed
 
+        --rust-version <VERSION>
+            Only list the versions that build with this rust version (e.g. 1.70), with --list.
+            This uses the rust-version each version declares, versions that don't declare one are kept.
+
+        --requires-feature <NAME>
+            Only list the versions that have this feature (or an optional dependency by this name), with --list.
+
     ARGS:
         <crate>  The name of the crate to retrieve information for.
 
@@ -774,6 +1476,14 @@ CONFIG:
     CONFIG:
         WHATFEATURES_THEME  [colorful, basic, palette, none]
                             This allows you to override the --theme flag with an environmental variable
+
+        WHATFEATURES_CACHE_MAX_SIZE  The maximum size of the local cache, e.g. 500M or 2G (powers of 1024)
+        WHATFEATURES_CACHE_MAX_AGE   How long a crate can go unused before it is removed, e.g. 90s, 45m, 12h, 30d or 4w
+                                     These can also be set in a [cache] table in the config file, which --cache-info
+                                     prints the path of. The environmental variables take precedence over it.
+                                     [cache]
+                                     max-size = "2G"
+                                     max-age = "30d"
 "#;
 
     println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
@@ -787,7 +1497,137 @@ CONFIG:
     std::process::exit(0)
 }
 
+fn print_cache_info() -> anyhow::Result<()> {
+    use crate::cache::{format_age, format_size};
+
+    let limits = crate::CacheLimits::from_env()?;
+    let cache = crate::Cache::open()?;
+
+    println!("cache directory: {}", cache.root().display());
+    println!("config file: {}", crate::cache::config_file()?.display());
+    println!("crates: {}", cache.entries().len());
+    println!("size: {}", format_size(cache.total_size()));
+    println!(
+        "max size: {}",
+        limits
+            .max_size
+            .map(format_size)
+            .unwrap_or_else(|| "unlimited".into())
+    );
+    println!(
+        "max age: {}",
+        limits
+            .max_age
+            .map(format_age)
+            .unwrap_or_else(|| "unlimited".into())
+    );
+
+    if cache.entries().is_empty() {
+        return Ok(());
+    }
+
+    println!("least recently used:");
+    let now = std::time::SystemTime::now();
+    for entry in cache.entries().iter().take(10) {
+        let age = now.duration_since(entry.last_access).unwrap_or_default();
+        let registry = if entry.registry == crate::CRATES_IO {
+            String::new()
+        } else {
+            format!(" @ {}", entry.registry)
+        };
+        println!(
+            "    {}/{}{}: {}, last used {} ago",
+            entry.name,
+            entry.version,
+            registry,
+            format_size(entry.size),
+            format_age(age)
+        );
+    }
+    Ok(())
+}
+
 fn print_version() -> ! {
     println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
     std::process::exit(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkgid() {
+        for input in [
+            "serde",
+            "serde:1.0.0",
+            "serde:~1.0",
+            "serde@my-registry",
+            "serde:1@my-registry",
+        ] {
+            let pkgid = input.parse::<PkgId>().unwrap();
+            assert_eq!(pkgid.to_string(), input);
+        }
+    }
+
+    fn parse(args: &[&str]) -> anyhow::Result<Args> {
+        let args = std::iter::once("whatfeatures").chain(args.iter().copied());
+        Args::parse_from(args.map(Into::into).collect())
+    }
+
+    #[test]
+    fn crate_argument() {
+        let args = parse(&["serde:1@my-registry"]).unwrap();
+        assert_eq!(args.pkgid.to_string(), "serde:1@my-registry");
+        assert!(!args.pkgid.is_local());
+
+        for path in [".", "./does-not-exist", "foo/Cargo.toml", "src"] {
+            let args = parse(&[path]).unwrap();
+            assert!(args.pkgid.is_local(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn crate_argument_errors() {
+        let err = parse(&["serde@1.0.0"]).unwrap_err();
+        assert!(
+            matches!(err.downcast_ref(), Some(Error::VersionAfterAt { .. })),
+            "{}",
+            err
+        );
+
+        let err = parse(&["bitflags@"]).unwrap_err();
+        assert!(
+            matches!(err.downcast_ref(), Some(Error::EmptyRegistry { .. })),
+            "{}",
+            err
+        );
+
+        let err = parse(&["serde:nope"]).unwrap_err();
+        assert!(
+            matches!(err.downcast_ref(), Some(Error::InvalidVersionReq { .. })),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn pkgid_errors() {
+        assert!(matches!(
+            "serde@1.0.0".parse::<PkgId>(),
+            Err(Error::VersionAfterAt { name, semver }) if name == "serde" && semver == "1.0.0"
+        ));
+        assert!(matches!(
+            "serde:1@^1".parse::<PkgId>(),
+            Err(Error::VersionAfterAt { .. })
+        ));
+        assert!(matches!(
+            "bitflags@".parse::<PkgId>(),
+            Err(Error::EmptyRegistry { .. })
+        ));
+        assert!(matches!(
+            "serde:nope".parse::<PkgId>(),
+            Err(Error::InvalidVersionReq { .. })
+        ));
+    }
+}
@@ -3,33 +3,68 @@ use std::collections::HashSet;
 use cargo_whatfeatures::*;
 
 fn real_main(mut args: Args) -> anyhow::Result<()> {
-    let options = cargo_whatfeatures::Options {
+    let mut options = cargo_whatfeatures::Options {
         print_features: !args.no_features,
         show_deps: args.show_deps,
         verbose: args.verbose,
         show_private: args.show_private,
         theme: args.theme,
+        target: None,
+        format: args.format,
     };
 
+    let registry = args.registry()?;
+
     let client = if args.offline {
         None
     } else {
-        Some(Client::new("https://crates.io"))
+        Some(Client::for_registry(registry.clone())?)
     };
 
     let name = args.pkgid.name();
 
     if args.list {
-        let versions = client
-            .as_ref()
-            .ok_or_else(|| OfflineError::List.to_error())?
-            .list_versions(name)
-            .map_err(|_| anyhow::anyhow!("cannot find a crate matching '{}'", &args.pkgid))?;
+        let mut versions = match &client {
+            Some(client) => {
+                let mut versions = client.list_versions(name).map_err(|_| {
+                    anyhow::anyhow!("cannot find a crate matching '{}'", &args.pkgid)
+                })?;
+
+                // the index doesn't have licenses (or timestamps for older crates), but they aren't required
+                let _ = client.fill_details(&mut versions);
+                versions
+            }
+            None => LocalIndex::for_registry(&registry)
+                .and_then(|index| index.list_versions(name))
+                .map_err(|_| OfflineError::List.to_registry_error(&registry))?,
+        };
 
         if versions.is_empty() {
             anyhow::bail!("no versions published for '{}'", &args.pkgid)
         }
 
+        if let Some(toolchain) = &args.rust_version {
+            versions.retain(|version| version.supports_rust(toolchain));
+            if versions.is_empty() {
+                anyhow::bail!(
+                    "no versions of '{}' build with rust {}",
+                    &args.pkgid,
+                    toolchain
+                )
+            }
+        }
+
+        if let Some(feature) = &args.requires_feature {
+            versions.retain(|version| version.has_feature(feature));
+            if versions.is_empty() {
+                anyhow::bail!(
+                    "no versions of '{}' have the feature '{}'",
+                    &args.pkgid,
+                    feature
+                )
+            }
+        }
+
         if versions.len() == 1 && versions[0].yanked {
             args.show_yanked.replace(YankStatus::Include);
         }
@@ -58,16 +93,145 @@ fn real_main(mut args: Args) -> anyhow::Result<()> {
             .map_err(Into::into);
     }
 
-    let mut out = std::io::stdout();
+    if let (Some(dep), PkgId::Local(path)) = (&args.resolved, &args.pkgid) {
+        let activated =
+            ActivatedFeatures::for_dependency(path, dep, args.offline, args.target.as_deref())?;
+        if args.json {
+            let json = cargo_whatfeatures::json::activated(&activated);
+            println!("{json}");
+            std::process::exit(0)
+        }
+
+        ActivationPrinter::new(&mut std::io::stdout(), &activated, args.theme).print()?;
+        return Ok(());
+    }
+
+    if let (Some(dep), PkgId::Local(path)) = (&args.apply, &args.pkgid) {
+        let enable = args.enable.as_deref().unwrap_or_default();
+        let disable = args.disable.as_deref().unwrap_or_default();
+
+        let mut manifest = Manifest::open(path)?;
+        let entries = manifest.find(dep)?;
+
+        // check every entry before changing anything
+        for entry in &entries {
+            let features = dependency_features(entry, &registry, &args)?;
+            for name in enable.iter().chain(disable) {
+                if !features.features.contains_key(name) {
+                    let mut available = features
+                        .features
+                        .keys()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>();
+                    available.sort_unstable();
+
+                    anyhow::bail!(
+                        "{} = {} (in [{}]) has no feature named '{}'. the features are: {}",
+                        features.name,
+                        features.version,
+                        entry.table,
+                        name,
+                        available.join(", ")
+                    )
+                }
+            }
+        }
+
+        let mut changed = vec![];
+        for entry in &entries {
+            for name in disable.iter().filter(|name| !entry.features.contains(name)) {
+                let msg = args.theme.warning.paint(format!(
+                    "WARNING: '{}' is not enabled for '{}' in [{}]",
+                    name, entry.key, entry.table
+                ));
+                eprintln!("{}", msg);
+            }
+            if manifest.apply(entry, enable, disable) {
+                changed.push(entry);
+            }
+        }
+
+        if changed.is_empty() {
+            println!("nothing to change in {}", manifest.path().display());
+            return Ok(());
+        }
+        manifest.save()?;
+
+        for entry in changed {
+            println!(
+                "updated '{}' in [{}] of {}",
+                entry.key,
+                entry.table,
+                manifest.path().display()
+            );
+        }
+        return Ok(());
+    }
+
+    if args.history {
+        let entries = match &client {
+            Some(client) => client
+                .list_entries(name)
+                .map_err(|_| anyhow::anyhow!("cannot find a crate matching '{}'", &args.pkgid))?,
+            None => LocalIndex::for_registry(&registry)
+                .and_then(|index| index.list_entries(name))
+                .map_err(|_| OfflineError::List.to_registry_error(&registry))?,
+        };
+
+        let semver = match &args.pkgid {
+            PkgId::Remote { semver, .. } => semver.as_deref(),
+            PkgId::Local(..) => None,
+        };
+        let history = FeatureHistory::from_entries(name, &entries, semver, args.pre_release)?;
+        if args.json {
+            let json = cargo_whatfeatures::json::history(&history);
+            println!("{json}");
+            std::process::exit(0)
+        }
+
+        HistoryPrinter::new(&mut std::io::stdout(), &history, args.theme).print()?;
+        return Ok(());
+    }
+
+    if let Some((old, new)) = &args.diff {
+        let features = |semver: &str| {
+            let pkgid = PkgId::Remote {
+                name: name.to_string(),
+                semver: Some(semver.to_string()),
+                registry: args.registry.clone(),
+            };
+            lookup_features(&pkgid, &client, &registry, &args)
+        };
+
+        let diff = FeatureDiff::new(&features(old)?, &features(new)?);
+        if args.json {
+            let json = cargo_whatfeatures::json::diff(&diff);
+            println!("{json}");
+            std::process::exit(0)
+        }
+
+        DiffPrinter::new(&mut std::io::stdout(), &diff, args.theme).print()?;
+        return Ok(());
+    }
 
-    let workspace = match cargo_whatfeatures::lookup(&args.pkgid, &client, args.local_only)? {
-        Lookup::Partial(vers) => {
-            let Version { name, version, .. } = &vers;
+    let mut out = std::io::stdout();
 
+    let mut workspace = match cargo_whatfeatures::lookup(
+        &args.pkgid,
+        &client,
+        &registry,
+        args.local_only,
+        args.pre_release,
+    )? {
+        Lookup::Partial(mut vers) => {
             if args.name_only {
+                if let Some(client) = &client {
+                    let _ = client.fill_details(std::slice::from_mut(&mut vers));
+                }
+
                 if args.json {
                     let json = cargo_whatfeatures::json::create_crates_from_versions(
-                        name,
+                        &vers.name,
                         Some(vers.clone()),
                     );
                     println!("{json}");
@@ -79,45 +243,54 @@ fn real_main(mut args: Args) -> anyhow::Result<()> {
                     .map_err(Into::into);
             }
 
-            let crate_ = match Registry::from_local()?.get(&name, &version) {
-                Some(crate_) => crate_.clone(),
-                None => match client
-                    .as_ref()
-                    .ok_or_else(|| OfflineError::CacheMiss.to_error())?
-                    .cache_crate(&name, &version)
-                {
-                    Ok(res) => res,
-                    Err(_err) => return cannot_lookup(&args.pkgid),
-                },
+            let crate_ = match cargo_whatfeatures::fetch_crate(&vers, &client, &registry) {
+                Ok(crate_) => crate_,
+                Err(err) if client.is_none() => return Err(err),
+                Err(_err) => return cannot_lookup(&args.pkgid),
             };
 
+            // warnings go to stderr, so they don't end up in the json
             if let YankState::Yanked = crate_.yanked {
                 use yansi::*;
-                println!(
-                    "{}. {}/{} has been yanked on crates.io",
+                eprintln!(
+                    "{}. {}/{} has been yanked on {}",
                     Paint::yellow("WARNING"),
                     crate_.name,
-                    crate_.version
+                    crate_.version,
+                    registry.name,
                 );
             }
 
             crate_.get_features()?
         }
 
-        pkg @ Lookup::LocalCache(..) | pkg @ Lookup::Workspace(..) => {
-            let local = matches!(pkg, Lookup::LocalCache { .. });
-            let pkg = match pkg {
-                Lookup::LocalCache(pkg) | Lookup::Workspace(pkg) => pkg,
+        pkg @ Lookup::LocalCache { .. } | pkg @ Lookup::Workspace(..) => {
+            let (pkg, yanked, possibly_old) = match pkg {
+                Lookup::LocalCache {
+                    workspace,
+                    yanked,
+                    possibly_old,
+                } => (workspace, Some(yanked), possibly_old),
+                Lookup::Workspace(pkg) => (pkg, None, false),
                 _ => unreachable!(),
             };
 
-            if local {
-                use std::io::Write as _;
+            if possibly_old {
                 let msg = args.theme.warning.paint(format!(
                     "WARNING: {}",
                     cargo_whatfeatures::labels::POSSIBLY_OLD_CRATE
                 ));
-                writeln!(out, "{}", msg)?;
+                eprintln!("{}", msg);
+            }
+
+            if let Some(yanked) = yanked {
+                if let (YankState::Yanked, Some(crate_)) = (yanked, pkg.map.values().next()) {
+                    let msg = args.theme.warning.paint(format!(
+                        "WARNING: {}/{} has been yanked on {}",
+                        crate_.name, crate_.version, registry.name
+                    ));
+                    eprintln!("{}", msg);
+                }
             }
 
             if args.name_only {
@@ -142,6 +315,75 @@ fn real_main(mut args: Args) -> anyhow::Result<()> {
         }
     };
 
+    if args.toml {
+        let features = single_crate(&workspace, &args, "--toml")?;
+        let enable = args.enable.as_deref().unwrap_or_default();
+        let line = DependencyLine::new(features, enable, !args.no_default_features)?;
+        for (name, by) in &line.implied {
+            let msg = args.theme.warning.paint(format!(
+                "WARNING: '{}' is already enabled by '{}'",
+                name, by
+            ));
+            eprintln!("{}", msg);
+        }
+
+        println!("{}", line);
+        return Ok(());
+    }
+
+    if args.scan {
+        let features = single_crate(&workspace, &args, "--scan")?;
+        let dir = features
+            .manifest_path
+            .as_deref()
+            .and_then(std::path::Path::parent)
+            .ok_or_else(|| anyhow::anyhow!("cannot find the source for '{}'", features.name))?;
+        let scan = SourceScan::scan(features, dir)?;
+        if args.json {
+            let json = cargo_whatfeatures::json::scan(&scan);
+            println!("{json}");
+            std::process::exit(0)
+        }
+
+        ScanPrinter::new(&mut std::io::stdout(), &scan, args.verbose, args.theme).print()?;
+        return Ok(());
+    }
+
+    if let Some(enable) = &args.enable {
+        let features = single_crate(&workspace, &args, "--enable")?;
+        let resolved = Resolver::new(features).resolve(enable)?;
+        if args.json {
+            let json = cargo_whatfeatures::json::resolved(features, &resolved);
+            println!("{json}");
+            std::process::exit(0)
+        }
+
+        ResolvedPrinter::new(&mut std::io::stdout(), features, &resolved, args.theme).print()?;
+        return Ok(());
+    }
+
+    if let Some(target) = &args.why {
+        let features = single_crate(&workspace, &args, "--why")?;
+        let why = Resolver::new(features).why(target)?;
+        if args.json {
+            let json = cargo_whatfeatures::json::why(features, &why);
+            println!("{json}");
+            std::process::exit(0)
+        }
+
+        WhyPrinter::new(&mut std::io::stdout(), features, &why, args.theme).print()?;
+        return Ok(());
+    }
+
+    if let Some(triple) = &args.target {
+        let target = Target::from_rustc(triple)?;
+        if args.other_targets {
+            options.target.replace(target);
+        } else {
+            workspace.retain_target(&target);
+        }
+    }
+
     if args.json {
         let json = cargo_whatfeatures::json::workspace(workspace);
         println!("{json}");
@@ -152,7 +394,99 @@ fn real_main(mut args: Args) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn cannot_lookup(pkgid: &PkgId) -> anyhow::Result<()> {
+/// Lookup the features for a specific version of a remote crate
+fn lookup_features(
+    pkgid: &PkgId,
+    client: &Option<Client>,
+    registry: &RegistryConfig,
+    args: &Args,
+) -> anyhow::Result<Features> {
+    let workspace = match lookup(pkgid, client, registry, false, args.pre_release)? {
+        Lookup::Partial(vers) => match fetch_crate(&vers, client, registry) {
+            Ok(crate_) => crate_.get_features()?,
+            Err(err) if client.is_none() => return Err(err),
+            Err(_err) => return cannot_lookup(pkgid),
+        },
+        Lookup::LocalCache { workspace, .. } | Lookup::Workspace(workspace) => workspace,
+    };
+
+    workspace
+        .map
+        .into_values()
+        .find(|features| features.name == pkgid.name())
+        .ok_or_else(|| anyhow::anyhow!("cannot find '{}' in its own package", pkgid))
+}
+
+/// Lookup the features for the version of a dependency that a `Cargo.toml` entry requires
+fn dependency_features(
+    entry: &DependencyEntry,
+    registry: &RegistryConfig,
+    args: &Args,
+) -> anyhow::Result<Features> {
+    match &entry.source {
+        DependencySource::Registry {
+            req,
+            registry: name,
+        } => {
+            let registry = match name {
+                Some(name) => RegistryConfig::from_cargo_config(name)?,
+                None => registry.clone(),
+            };
+            let client = if args.offline {
+                None
+            } else {
+                Some(Client::for_registry(registry.clone())?)
+            };
+            let pkgid = PkgId::Remote {
+                name: entry.package.clone(),
+                semver: Some(req.clone()),
+                registry: name.clone(),
+            };
+            lookup_features(&pkgid, &client, &registry, args)
+        }
+        DependencySource::Path(path) => {
+            let pkgid = PkgId::Local(path.clone());
+            match lookup(&pkgid, &None, registry, false, args.pre_release)? {
+                Lookup::Workspace(workspace) => workspace
+                    .map
+                    .into_values()
+                    .find(|features| features.name == entry.package)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("cannot find '{}' in '{}'", entry.package, path.display())
+                    }),
+                _ => cannot_lookup(&pkgid),
+            }
+        }
+        DependencySource::Git(url) => anyhow::bail!(
+            "cannot find the features of '{}', it is a git dependency ({})",
+            entry.key,
+            url
+        ),
+    }
+}
+
+/// Get the only crate in the workspace, for the flags that only make sense with one crate
+fn single_crate<'a>(
+    workspace: &'a cargo_whatfeatures::Workspace,
+    args: &Args,
+    flag: &str,
+) -> anyhow::Result<&'a cargo_whatfeatures::Features> {
+    let mut members = workspace
+        .map
+        .values()
+        .filter(|features| features.published || args.show_private);
+
+    match (members.next(), members.next()) {
+        (Some(features), None) => Ok(features),
+        (None, ..) => anyhow::bail!("no crates were found"),
+        (Some(..), Some(..)) => anyhow::bail!(
+            "`{}` can only be used with a single crate. try using -t, --this-crate",
+            flag
+        ),
+    }
+}
+
+fn cannot_lookup<T>(pkgid: &PkgId) -> anyhow::Result<T> {
     let mut out = format!("cannot lookup crate '{}'.", &pkgid);
     if let PkgId::Remote {
         semver: Some(semver),
@@ -0,0 +1,574 @@
+use anyhow::Context as _;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+/// The file, inside of the cache directory, where the last access time of each crate is kept
+const ACCESS_FILE: &str = "access.json";
+
+/// The environment variable for the maximum size of the cache
+pub const MAX_SIZE_ENV: &str = "WHATFEATURES_CACHE_MAX_SIZE";
+
+/// The environment variable for how long a crate can go unused before it is removed from the cache
+pub const MAX_AGE_ENV: &str = "WHATFEATURES_CACHE_MAX_AGE";
+
+/// The limits for the cache
+///
+/// Nothing is removed by default
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheLimits {
+    /// The maximum total size of the cache, in bytes
+    pub max_size: Option<u64>,
+    /// How long a crate can go unused before it is removed
+    pub max_age: Option<Duration>,
+}
+
+impl CacheLimits {
+    /// Read the limits from the environment, falling back to the `[cache]` table of the config file
+    ///
+    /// The sizes are like `500M` or `2G` and the ages are like `12h`, `30d` or `4w`
+    pub fn from_env() -> anyhow::Result<Self> {
+        #[derive(Default, serde::Deserialize)]
+        struct Config {
+            #[serde(default)]
+            cache: CacheConfig,
+        }
+
+        #[derive(Default, serde::Deserialize)]
+        #[serde(rename_all = "kebab-case")]
+        struct CacheConfig {
+            max_size: Option<String>,
+            max_age: Option<String>,
+        }
+
+        let path = config_file()?;
+        let config = match std::fs::read_to_string(&path) {
+            Ok(data) => toml::from_str(&data)
+                .with_context(|| anyhow::anyhow!("cannot parse '{}'", path.display()))?,
+            Err(..) => Config::default(),
+        };
+
+        let env = |key| std::env::var(key).ok().filter(|s| !s.is_empty());
+        let max_size = env(MAX_SIZE_ENV).or(config.cache.max_size);
+        let max_age = env(MAX_AGE_ENV).or(config.cache.max_age);
+
+        Ok(Self {
+            max_size: max_size.as_deref().map(parse_size).transpose()?,
+            max_age: max_age.as_deref().map(parse_age).transpose()?,
+        })
+    }
+
+    /// Whether there are no limits
+    pub fn is_unlimited(&self) -> bool {
+        self.max_size.is_none() && self.max_age.is_none()
+    }
+}
+
+/// The config file that the cache limits can be set in
+pub fn config_file() -> anyhow::Result<PathBuf> {
+    crate::util::config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// A crate in our cache
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    /// Crate name
+    pub name: String,
+    /// Crate version
+    pub version: String,
+    /// The registry the crate came from
+    pub registry: String,
+    /// Path to the crate directory
+    pub path: PathBuf,
+    /// How many bytes the crate uses
+    pub size: u64,
+    /// When the crate was last used
+    pub last_access: SystemTime,
+}
+
+impl CacheEntry {
+    fn new(registry: &str, path: PathBuf, size: u64, last_access: SystemTime) -> Option<Self> {
+        use crate_version_parse::CrateVersion;
+
+        let dir = path.file_name()?.to_str()?;
+        let CrateVersion { name, version } = CrateVersion::try_parse(dir).ok()?;
+        Some(Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            registry: registry.to_string(),
+            path,
+            size,
+            last_access,
+        })
+    }
+}
+
+/// What a garbage collection of the cache did
+#[derive(Debug, Default)]
+pub struct Collected {
+    /// The crates that were removed
+    pub removed: Vec<CacheEntry>,
+    /// The crates that couldn't be removed, and why
+    pub failed: Vec<(CacheEntry, anyhow::Error)>,
+}
+
+/// Our cache of downloaded crates, for crates.io and every other registry
+///
+/// This doesn't include cargo's own registry
+pub struct Cache {
+    root: PathBuf,
+    entries: Vec<CacheEntry>,
+}
+
+impl Cache {
+    /// Open the cache directory, finding every crate in it and measuring its size
+    pub fn open() -> anyhow::Result<Self> {
+        let root = crate::util::cache_dir()?;
+        let access = read_access(&root);
+
+        let registries = root.join(crate::util::REGISTRIES_DIR);
+        let mut dirs = vec![(crate::CRATES_IO.to_string(), root.clone())];
+        dirs.extend(
+            registries
+                .read_dir()
+                .into_iter()
+                .flat_map(|dir| dir.flatten())
+                .filter(|dir| dir.path().is_dir())
+                .map(|dir| (dir.file_name().to_string_lossy().to_string(), dir.path())),
+        );
+
+        let mut entries = vec![];
+        for (registry, base) in dirs {
+            for dir in base
+                .read_dir()
+                .into_iter()
+                .flat_map(|dir| dir.flatten())
+                .map(|dir| dir.path())
+                .filter(|path| path.is_dir() && *path != registries)
+            {
+                let last_access = access
+                    .get(&access_key(&root, &dir))
+                    .map(|access| from_secs(access.last_access()))
+                    .or_else(|| dir.metadata().and_then(|md| md.modified()).ok())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+
+                let size = dir_size(&dir);
+                entries.extend(CacheEntry::new(&registry, dir, size, last_access));
+            }
+        }
+
+        Ok(Self::new(root, entries))
+    }
+
+    /// Load the crates recorded in the access file, without looking at the rest of the cache
+    fn recorded(root: PathBuf) -> Self {
+        let entries = read_access(&root)
+            .into_iter()
+            .filter_map(|(key, access)| {
+                let path = root.join(&key);
+                if !path.is_dir() {
+                    return None;
+                }
+
+                // older access files didn't record the size
+                let size = access.size().unwrap_or_else(|| dir_size(&path));
+                let registry = match key.split('/').collect::<Vec<_>>()[..] {
+                    [dir, registry, _] if dir == crate::util::REGISTRIES_DIR => registry,
+                    _ => crate::CRATES_IO,
+                };
+                CacheEntry::new(registry, path, size, from_secs(access.last_access()))
+            })
+            .collect();
+
+        Self::new(root, entries)
+    }
+
+    fn new(root: PathBuf, mut entries: Vec<CacheEntry>) -> Self {
+        // the least recently used crates are first
+        entries.sort_by_key(|entry| entry.last_access);
+        Self { root, entries }
+    }
+
+    /// The cache directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Every crate in the cache, the least recently used first
+    pub fn entries(&self) -> &[CacheEntry] {
+        &self.entries
+    }
+
+    /// The total size of the cache, in bytes
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    /// Remove the crates that are older than the max age, then the least recently used crates
+    /// until the cache fits in the max size
+    ///
+    /// A crate that cannot be removed is skipped and reported in [`Collected::failed`]
+    pub fn collect_garbage(&mut self, limits: &CacheLimits) -> anyhow::Result<Collected> {
+        self.collect(limits, &[])
+    }
+
+    /// Record that this crate was used, and then remove anything that is over the limits
+    ///
+    /// No crate used by this process is removed. This only reads the sizes recorded in the access
+    /// file, the full scan of the cache is left to [`Cache::open`]
+    pub(crate) fn used(path: &Path) {
+        static USED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+        let root = match crate::util::cache_dir() {
+            Ok(root) => root,
+            Err(..) => return,
+        };
+
+        let mut used = USED.lock().unwrap_or_else(|err| err.into_inner());
+        if !used.iter().any(|p| p == path) {
+            used.push(path.to_path_buf());
+        }
+
+        let mut cache = Self::recorded(root);
+        cache.touch(path);
+
+        // an invalid limit is reported by --gc and --cache-info, this shouldn't stop a lookup
+        let limits = CacheLimits::from_env().unwrap_or_default();
+        let _ = cache.collect(&limits, &used);
+    }
+
+    fn touch(&mut self, path: &Path) {
+        let entry = match self.entries.iter().position(|entry| entry.path == path) {
+            Some(pos) => Some(self.entries.remove(pos)),
+            None => {
+                let registry = match path.parent() {
+                    Some(parent) if parent == self.root => crate::CRATES_IO.to_string(),
+                    Some(parent) => parent
+                        .file_name()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    None => return,
+                };
+                CacheEntry::new(
+                    &registry,
+                    path.to_path_buf(),
+                    dir_size(path),
+                    SystemTime::now(),
+                )
+            }
+        };
+
+        if let Some(mut entry) = entry {
+            entry.last_access = SystemTime::now();
+            self.entries.push(entry);
+        }
+    }
+
+    fn collect(&mut self, limits: &CacheLimits, keep: &[PathBuf]) -> anyhow::Result<Collected> {
+        let now = SystemTime::now();
+        let mut total = self.total_size();
+
+        let mut collected = Collected::default();
+        let mut kept = vec![];
+        for entry in std::mem::take(&mut self.entries) {
+            let too_old = limits.max_age.is_some_and(|max_age| {
+                now.duration_since(entry.last_access)
+                    .is_ok_and(|age| age > max_age)
+            });
+            let too_big = limits.max_size.is_some_and(|max_size| total > max_size);
+
+            if !(too_old || too_big) || keep.contains(&entry.path) {
+                kept.push(entry);
+                continue;
+            }
+
+            match std::fs::remove_dir_all(&entry.path) {
+                Ok(()) => {
+                    total -= entry.size;
+                    collected.removed.push(entry);
+                }
+                Err(err) => {
+                    let err = anyhow::Error::new(err)
+                        .context(format!("cannot remove {}", entry.path.display()));
+                    collected.failed.push((entry.clone(), err));
+                    kept.push(entry);
+                }
+            }
+        }
+        self.entries = kept;
+
+        // this also forgets about anything that isn't in the cache anymore (e.g. it was purged)
+        let access = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let last_access = entry
+                    .last_access
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                let access = Access {
+                    last_access,
+                    size: entry.size,
+                };
+                (access_key(&self.root, &entry.path), access)
+            })
+            .collect();
+        write_access(&self.root, &access)?;
+
+        Ok(collected)
+    }
+}
+
+/// What the access file records about a crate
+#[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct Access {
+    /// When the crate was last used, in seconds since the epoch
+    last_access: u64,
+    /// How many bytes the crate uses
+    size: u64,
+}
+
+#[derive(Copy, Clone, Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum StoredAccess {
+    Access(Access),
+    // older versions only recorded the last access time
+    LastAccess(u64),
+}
+
+impl StoredAccess {
+    fn last_access(&self) -> u64 {
+        match self {
+            Self::Access(access) => access.last_access,
+            Self::LastAccess(secs) => *secs,
+        }
+    }
+
+    fn size(&self) -> Option<u64> {
+        match self {
+            Self::Access(access) => Some(access.size),
+            Self::LastAccess(..) => None,
+        }
+    }
+}
+
+fn from_secs(secs: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+fn access_key(root: &Path, path: &Path) -> String {
+    let path = path.strip_prefix(root).unwrap_or(path);
+    path.iter()
+        .map(|s| s.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn read_access(root: &Path) -> BTreeMap<String, StoredAccess> {
+    std::fs::read(root.join(ACCESS_FILE))
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+fn write_access(root: &Path, access: &BTreeMap<String, Access>) -> anyhow::Result<()> {
+    let path = root.join(ACCESS_FILE);
+    let data = serde_json::to_vec(access)?;
+    std::fs::write(&path, data).with_context(|| anyhow::anyhow!("cannot write {}", path.display()))
+}
+
+fn dir_size(path: &Path) -> u64 {
+    path.read_dir()
+        .into_iter()
+        .flat_map(|dir| dir.flatten())
+        .map(|entry| match entry.file_type() {
+            Ok(ty) if ty.is_dir() => dir_size(&entry.path()),
+            Ok(..) => entry.metadata().map(|md| md.len()).unwrap_or_default(),
+            Err(..) => 0,
+        })
+        .sum()
+}
+
+/// Parse a size, like `500M`, `2G` or `1048576`
+///
+/// The units are powers of 1024
+pub fn parse_size(s: &str) -> anyhow::Result<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let scale: u64 = match unit
+        .trim()
+        .trim_end_matches(['b', 'B'])
+        .trim_end_matches('i')
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => anyhow::bail!("invalid size '{}'. expected something like 500M or 2G", s),
+    };
+
+    let invalid = || anyhow::anyhow!("invalid size '{}'. expected something like 500M or 2G", s);
+    let size = number.parse::<f64>().map_err(|_| invalid())? * scale as f64;
+    // anything at or above 2^64 would be saturated by the cast
+    if !size.is_finite() || size >= u64::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(size as u64)
+}
+
+/// Parse an age, like `90s`, `45m`, `12h`, `30d` or `4w`
+pub fn parse_age(s: &str) -> anyhow::Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let scale = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => anyhow::bail!(
+            "invalid age '{}'. expected something like 12h, 30d or 4w",
+            s
+        ),
+    };
+
+    let invalid = || {
+        anyhow::anyhow!(
+            "invalid age '{}'. expected something like 12h, 30d or 4w",
+            s
+        )
+    };
+    let number = number.parse::<u64>().map_err(|_| invalid())?;
+    number
+        .checked_mul(scale)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+/// Format a size, like `1.5 MiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{:.1} {}", size, unit)
+}
+
+/// Format an age as its largest unit, like `3 days`
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (n, unit) = match secs {
+        s if s >= 60 * 60 * 24 * 7 => (s / (60 * 60 * 24 * 7), "week"),
+        s if s >= 60 * 60 * 24 => (s / (60 * 60 * 24), "day"),
+        s if s >= 60 * 60 => (s / (60 * 60), "hour"),
+        s if s >= 60 => (s / 60, "minute"),
+        s => (s, "second"),
+    };
+    if n == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", n, unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        for (size, expected) in [
+            ("1048576", 1 << 20),
+            ("0", 0),
+            ("512b", 512),
+            ("4k", 4 << 10),
+            ("4K", 4 << 10),
+            ("4KB", 4 << 10),
+            ("4KiB", 4 << 10),
+            ("500M", 500 << 20),
+            ("500 MiB", 500 << 20),
+            ("2G", 2 << 30),
+            ("1T", 1 << 40),
+            ("1.5G", 3 << 29),
+            ("0.5k", 512),
+            ("  10M  ", 10 << 20),
+        ] {
+            assert_eq!(parse_size(size).unwrap(), expected, "{}", size);
+        }
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        for size in ["", "   ", "M", "10X", "10 megabytes", "-1M", "1.2.3M", "."] {
+            assert!(parse_size(size).is_err(), "{}", size);
+        }
+
+        // too big for a u64, or for an f64
+        let infinite = format!("1{}", "0".repeat(400));
+        for size in ["99999999999T", "18446744073709551616", &infinite] {
+            assert!(parse_size(size).is_err(), "{}", size);
+        }
+    }
+
+    #[test]
+    fn ages() {
+        for (age, expected) in [
+            ("90s", 90),
+            ("45m", 45 * 60),
+            ("12h", 12 * 60 * 60),
+            ("30d", 30 * 60 * 60 * 24),
+            ("4w", 4 * 60 * 60 * 24 * 7),
+            ("0d", 0),
+            (" 1 d ", 60 * 60 * 24),
+        ] {
+            assert_eq!(
+                parse_age(age).unwrap(),
+                Duration::from_secs(expected),
+                "{}",
+                age
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_ages() {
+        // ages are whole numbers, with a unit
+        for age in [
+            "",
+            "   ",
+            "30",
+            "d",
+            "1.5h",
+            "10y",
+            "10D",
+            "-1d",
+            "9999999999999999w",
+            "18446744073709551616s",
+        ] {
+            assert!(parse_age(age).is_err(), "{}", age);
+        }
+    }
+}
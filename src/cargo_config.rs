@@ -0,0 +1,248 @@
+use crate::index::CRATES_IO_INDEX;
+use anyhow::Context as _;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// The name cargo uses for crates.io
+pub const CRATES_IO: &str = "crates-io";
+
+/// A remote registry that crates can be looked up in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegistryConfig {
+    /// The name of the registry (e.g. `crates-io`)
+    pub name: String,
+    /// The url of the sparse index for the registry
+    pub index: String,
+}
+
+impl RegistryConfig {
+    /// The crates.io registry
+    pub fn crates_io() -> Self {
+        Self {
+            name: CRATES_IO.to_string(),
+            index: CRATES_IO_INDEX.to_string(),
+        }
+    }
+
+    /// Create a registry from just its index url, it'll be named after its host
+    pub fn from_index(index: impl ToString) -> Self {
+        let index = normalize_index(&index.to_string());
+        if index == CRATES_IO_INDEX {
+            return Self::crates_io();
+        }
+
+        Self {
+            name: host_of(&index).to_string(),
+            index,
+        }
+    }
+
+    /// Find the named registry in cargo's configuration
+    ///
+    /// This uses `CARGO_REGISTRIES_<NAME>_INDEX` if its set, otherwise the `[registries.<name>]` tables
+    /// from the `.cargo/config.toml` files found from the current directory upwards and in `$CARGO_HOME`
+    pub fn from_cargo_config(name: &str) -> anyhow::Result<Self> {
+        if name == CRATES_IO {
+            return Ok(Self::crates_io());
+        }
+
+        let index = match std::env::var(env_key(name, "INDEX")) {
+            Ok(index) => index,
+            Err(..) => CargoConfig::load()?
+                .registries
+                .remove(name)
+                .and_then(|table| table.index)
+                .with_context(|| {
+                    anyhow::anyhow!("registry '{}' was not found in the cargo config", name)
+                })?,
+        };
+
+        anyhow::ensure!(
+            index.starts_with("sparse+"),
+            "registry '{}' uses a git index ({}). only sparse registries are supported",
+            name,
+            index
+        );
+
+        Ok(Self {
+            name: name.to_string(),
+            index: normalize_index(&index),
+        })
+    }
+
+    /// Whether this is crates.io
+    pub fn is_crates_io(&self) -> bool {
+        self.name == CRATES_IO
+    }
+
+    /// The host of the index, e.g. `index.crates.io`
+    pub fn host(&self) -> &str {
+        host_of(&self.index)
+    }
+
+    /// The directory crates from this registry are cached in
+    pub fn cache_dir(&self) -> anyhow::Result<PathBuf> {
+        let base = crate::util::cache_dir()?;
+        if self.is_crates_io() {
+            return Ok(base);
+        }
+        Ok(base.join(crate::util::REGISTRIES_DIR).join(&self.name))
+    }
+
+    /// Whether this directory name from cargo's `registry/src` belongs to this registry
+    ///
+    /// Cargo names these directories as `<host>-<hash>`
+    pub(crate) fn owns_cargo_dir(&self, dir_name: &str) -> bool {
+        let matches = |host: &str| {
+            dir_name
+                .strip_prefix(host)
+                .filter(|hash| hash.starts_with('-'))
+                .is_some()
+        };
+
+        // older versions of cargo used the git index on github for crates.io
+        matches(self.host()) || self.is_crates_io() && matches("github.com")
+    }
+}
+
+/// The parts of cargo's configuration that we care about
+#[derive(Default, serde::Deserialize)]
+pub(crate) struct CargoConfig {
+    #[serde(default)]
+    pub registries: HashMap<String, RegistryTable>,
+    #[serde(default)]
+    pub registry: GlobalRegistryTable,
+}
+
+/// A `[registries.<name>]` table
+#[derive(Default, serde::Deserialize)]
+pub(crate) struct RegistryTable {
+    pub index: Option<String>,
+    pub token: Option<String>,
+    #[serde(rename = "credential-provider")]
+    pub credential_provider: Option<ProviderSpec>,
+}
+
+/// The `[registry]` table
+#[derive(Default, serde::Deserialize)]
+pub(crate) struct GlobalRegistryTable {
+    #[serde(rename = "global-credential-providers")]
+    pub global_credential_providers: Option<Vec<String>>,
+}
+
+/// A credential provider, either as a single string or as a list of the program and its arguments
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ProviderSpec {
+    String(String),
+    List(Vec<String>),
+}
+
+impl ProviderSpec {
+    /// Split the provider into its program and arguments
+    pub(crate) fn to_args(&self) -> Vec<String> {
+        match self {
+            Self::String(s) => s.split_whitespace().map(ToString::to_string).collect(),
+            Self::List(list) => list.clone(),
+        }
+    }
+}
+
+impl CargoConfig {
+    /// Load and merge the config files, the ones closer to the current directory take precedence
+    ///
+    /// The credentials file in `$CARGO_HOME` is merged in last
+    pub(crate) fn load() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        for path in config_files()?
+            .iter()
+            .rev()
+            .chain(credentials_file().iter())
+        {
+            let data = std::fs::read_to_string(path)
+                .with_context(|| anyhow::anyhow!("cannot read {}", path.display()))?;
+            let other = toml::from_str(&data)
+                .with_context(|| anyhow::anyhow!("cannot parse {}", path.display()))?;
+            config.merge(other);
+        }
+        Ok(config)
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (name, table) in other.registries {
+            let this = self.registries.entry(name).or_default();
+            if table.index.is_some() {
+                this.index = table.index;
+            }
+            if table.token.is_some() {
+                this.token = table.token;
+            }
+            if table.credential_provider.is_some() {
+                this.credential_provider = table.credential_provider;
+            }
+        }
+
+        if other.registry.global_credential_providers.is_some() {
+            self.registry.global_credential_providers = other.registry.global_credential_providers;
+        }
+    }
+}
+
+/// The cargo config files, from the nearest to the furthest
+fn config_files() -> anyhow::Result<Vec<PathBuf>> {
+    fn find_in(dir: &Path) -> Option<PathBuf> {
+        // cargo prefers the file without the extension, if both exist
+        ["config", "config.toml"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    }
+
+    let mut files = std::env::current_dir()?
+        .ancestors()
+        .filter_map(|dir| find_in(&dir.join(".cargo")))
+        .collect::<Vec<_>>();
+
+    if let Some(path) = home::cargo_home().ok().as_deref().and_then(find_in) {
+        if !files.contains(&path) {
+            files.push(path)
+        }
+    }
+
+    Ok(files)
+}
+
+/// The credentials file in `$CARGO_HOME`, if it exists
+fn credentials_file() -> Option<PathBuf> {
+    let home = home::cargo_home().ok()?;
+    ["credentials", "credentials.toml"]
+        .iter()
+        .map(|name| home.join(name))
+        .find(|path| path.is_file())
+}
+
+/// The environment variable cargo uses for this registry key (e.g. `CARGO_REGISTRIES_MY_REGISTRY_INDEX`)
+pub(crate) fn env_key(name: &str, key: &str) -> String {
+    format!(
+        "CARGO_REGISTRIES_{}_{}",
+        name.to_uppercase().replace('-', "_"),
+        key
+    )
+}
+
+fn normalize_index(index: &str) -> String {
+    index
+        .strip_prefix("sparse+")
+        .unwrap_or(index)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn host_of(index: &str) -> &str {
+    let index = index.strip_prefix("sparse+").unwrap_or(index);
+    let index = index.split_once("://").map_or(index, |(_, rest)| rest);
+    let host = index.split('/').next().unwrap_or(index);
+    host.split(':').next().unwrap_or(host)
+}
@@ -1,30 +1,111 @@
-use crate::registry::Crate;
+use crate::{
+    cargo_config::RegistryConfig,
+    features::{Features, Workspace},
+    index::{IndexConfig, IndexEntry},
+    registry::Crate,
+};
 use anyhow::Context as _;
+use std::collections::BTreeSet;
 use time::format_description::FormatItem;
 
-/// An HTTP client for interacting with crates.io
+/// An HTTP client for interacting with crates.io (or any sparse registry)
 pub struct Client {
-    host: String,
+    registry: RegistryConfig,
+    config: IndexConfig,
+    token: Option<String>,
 }
 
 impl Client {
+    /// Create a new HTTP client for crates.io
+    pub fn crates_io() -> anyhow::Result<Self> {
+        Self::for_registry(RegistryConfig::crates_io())
+    }
+
     /// Create a new HTTP client with the provided host (e.g. `https://crates.io` or `http://localhost`)
+    ///
+    /// The host is used for the web API and for downloads, the crates are still looked up in the crates.io index.
+    /// Use [`Client::for_index`] or [`Client::for_registry`] for other registries
     pub fn new(host: impl ToString) -> Self {
+        let host = host.to_string();
+        let host = host.trim_end_matches('/');
         Self {
-            host: host.to_string(),
+            registry: RegistryConfig::crates_io(),
+            config: IndexConfig {
+                dl: format!("{host}/api/v1/crates"),
+                api: Some(host.to_string()),
+                auth_required: false,
+            },
+            token: None,
         }
     }
 
+    /// Create a new HTTP client with the provided sparse index (e.g. `https://index.crates.io` or `http://localhost`)
+    ///
+    /// See [`Client::for_registry`]
+    pub fn for_index(index: impl ToString) -> anyhow::Result<Self> {
+        Self::for_registry(RegistryConfig::from_index(index))
+    }
+
+    /// Create a new HTTP client for this registry
+    ///
+    /// This fetches the `config.json` from the index to find the download and api urls.
+    ///
+    /// If the registry requires authentication, the token is found the same way cargo finds it
+    pub fn for_registry(registry: RegistryConfig) -> anyhow::Result<Self> {
+        let url = format!("{}/config.json", registry.index);
+        let context = || anyhow::anyhow!("read the index config for: {}", registry.name);
+
+        // like cargo, only look for a token once the registry asks for one
+        let mut token = None;
+        let config: IndexConfig = match Self::fetch_json(&url, None) {
+            Ok(config) => config,
+            Err(err) if registry.is_crates_io() => return Err(err.context(context())),
+            Err(err) => {
+                token = crate::credentials::find_token(&registry)?;
+                match token.as_deref() {
+                    Some(token) => Self::fetch_json(&url, Some(token)).with_context(context)?,
+                    None => return Err(err.context(context())),
+                }
+            }
+        };
+
+        if !config.auth_required {
+            token.take();
+        } else if token.is_none() {
+            token = crate::credentials::find_token(&registry)?;
+            anyhow::ensure!(
+                token.is_some(),
+                "registry '{}' requires authentication, but no token was found",
+                registry.name
+            );
+        }
+
+        Ok(Self {
+            registry,
+            config,
+            token,
+        })
+    }
+
+    /// The registry this client is using
+    pub fn registry(&self) -> &RegistryConfig {
+        &self.registry
+    }
+
     /// Lookup and cache the latest version for this crate
     pub fn cache_latest(&self, crate_name: &str) -> anyhow::Result<Crate> {
-        let Version { version, .. } = self.get_latest(crate_name)?;
+        let Version { version, .. } = self.get_latest(crate_name, false)?;
         self.cache_crate(crate_name, &version)
     }
 
     /// Lookup and cache the specified version for this crate
     pub fn cache_crate(&self, crate_name: &str, crate_version: &str) -> anyhow::Result<Crate> {
         let (yanked, data) = self.download_crate(crate_name, crate_version)?;
-        crate::util::extract_crate(&data, crate_name, crate_version).map(|path| Crate {
+        let base = self.registry.cache_dir()?;
+        let path = crate::util::extract_crate(&base, &data, crate_name, crate_version)?;
+        crate::cache::Cache::used(&path);
+
+        Ok(Crate {
             name: crate_name.to_string(),
             version: crate_version.to_string(),
             path,
@@ -33,31 +114,119 @@ impl Client {
     }
 
     /// Get the latest version for this crate
-    pub fn get_latest(&self, crate_name: &str) -> anyhow::Result<Version> {
-        self.list_versions(crate_name)?
-            .into_iter()
-            .find(|s| !s.yanked)
-            .ok_or_else(|| anyhow::anyhow!("no available version for: {}", crate_name))
+    ///
+    /// Pre-releases are only considered if `pre_release` is set, or if there are no other versions
+    pub fn get_latest(&self, crate_name: &str, pre_release: bool) -> anyhow::Result<Version> {
+        let versions = self.list_versions(crate_name)?;
+        crate::util::find_latest(
+            versions.into_iter().filter(|s| !s.yanked),
+            |s| &s.version,
+            pre_release,
+        )
+        .ok_or_else(|| anyhow::anyhow!("no available version for: {}", crate_name))
     }
 
-    /// Get the latest version for this crate
+    /// Get the newest version for this crate that matches the requirement
+    ///
+    /// An exact version (e.g. `1.0.3`) can be yanked, otherwise only non-yanked versions are considered
     pub fn get_version(&self, crate_name: &str, semver: &str) -> anyhow::Result<Version> {
-        self.list_versions(crate_name)?
-            .into_iter()
-            .find(|s| s.version == semver)
-            .ok_or_else(|| anyhow::anyhow!("no available version for: {} = {}", crate_name, semver))
+        let exact = crate::util::is_exact_version(semver);
+        let versions = self.list_versions(crate_name)?;
+        crate::util::find_matching(
+            versions.into_iter().filter(|s| exact || !s.yanked),
+            |s| &s.version,
+            semver,
+        )
+        .ok_or_else(|| anyhow::anyhow!("no available version for: {} = {}", crate_name, semver))
     }
 
-    /// Get all versions for this crate
+    /// Get all versions for this crate, newest first
+    ///
+    /// These are read from the index, so `license` (and `created_at` and `rust_version`, for older entries) is not filled in.
+    /// Use [`Client::fill_details`] for those
     pub fn list_versions(&self, crate_name: &str) -> anyhow::Result<Vec<Version>> {
+        let mut versions = self
+            .list_entries(crate_name)?
+            .iter()
+            .map(|entry| Version {
+                dl_path: self.config.download_url(entry),
+                ..Version::from_index(entry)
+            })
+            .collect::<Vec<_>>();
+        versions
+            .sort_by(|left, right| crate::util::compare_versions(&right.version, &left.version));
+        Ok(versions)
+    }
+
+    /// Get all of the index entries for this crate, in the order they were published
+    pub fn list_entries(&self, crate_name: &str) -> anyhow::Result<Vec<IndexEntry>> {
+        let url = format!(
+            "{}/{}",
+            self.registry.index,
+            crate::index::entry_path(crate_name)
+        );
+        let data = Self::fetch_text(&url, self.token.as_deref())
+            .with_context(|| anyhow::anyhow!("list versions for: {}", crate_name))?;
+        crate::index::parse_entries(&data)
+            .with_context(|| anyhow::anyhow!("parse the index for: {}", crate_name))
+    }
+
+    /// Get the features and dependencies for this crate version, straight from the index
+    ///
+    /// Unlike [`Crate::get_features`] this does not need to download the crate
+    pub fn get_features(&self, crate_name: &str, crate_version: &str) -> anyhow::Result<Workspace> {
+        self.list_entries(crate_name)?
+            .iter()
+            .find(|entry| entry.vers == crate_version)
+            .map(|entry| Workspace::from_index(entry, &self.registry.index))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "no available version for: {} = {}",
+                    crate_name,
+                    crate_version
+                )
+            })
+    }
+
+    /// Fill in the fields that the index does not have (`license`, and `created_at` and `rust_version` for older entries) using the web API
+    ///
+    /// This is a no-op if the registry does not have an api
+    pub fn fill_details(&self, versions: &mut [Version]) -> anyhow::Result<()> {
         #[derive(serde::Deserialize)]
         struct Resp {
-            versions: Vec<Version>,
+            versions: Vec<ApiVersion>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct ApiVersion {
+            num: String,
+            license: Option<String>,
+            #[serde(with = "time::serde::rfc3339")]
+            created_at: time::OffsetDateTime,
+            #[serde(default)]
+            rust_version: Option<String>,
+        }
+
+        let (api, name) = match (&self.config.api, versions.first()) {
+            (Some(api), Some(version)) => (api.trim_end_matches('/'), version.name.clone()),
+            _ => return Ok(()),
+        };
+
+        let url = format!("{}/api/v1/crates/{}", api, name);
+        let resp: Resp = Self::fetch_json(&url, self.token.as_deref())
+            .with_context(|| anyhow::anyhow!("get version details for: {}", name))?;
+
+        for details in resp.versions {
+            if let Some(version) = versions.iter_mut().find(|v| v.version == details.num) {
+                version.license = details.license;
+                version.created_at.replace(details.created_at);
+                if details.rust_version.is_some() {
+                    version.rust_version = details.rust_version;
+                }
+            }
         }
 
-        self.fetch_json(&format!("/api/v1/crates/{}", crate_name))
-            .map(|resp: Resp| resp.versions)
-            .with_context(|| anyhow::anyhow!("list versions for: {}", crate_name))
+        Ok(())
     }
 }
 
@@ -67,55 +236,41 @@ impl Client {
         crate_name: &str,
         crate_version: &str,
     ) -> anyhow::Result<(bool, Vec<u8>)> {
-        #[derive(Debug, serde::Deserialize)]
-        struct Resp {
-            version: Version,
-        }
-
         let version = self
-            .fetch_json(&format!("/api/v1/crates/{}/{}", crate_name, crate_version))
-            .map(|resp: Resp| resp.version)
+            .get_version(crate_name, crate_version)
             .with_context(|| anyhow::anyhow!("download crate {}/{}", crate_name, crate_version))?;
 
-        anyhow::ensure!(version.name == crate_name, "received the wrong crate");
-        anyhow::ensure!(
-            version.version == crate_version,
-            "received the wrong version"
-        );
         anyhow::ensure!(!version.dl_path.is_empty(), "no download path available");
 
-        self.fetch_bytes(&version.dl_path)
+        Self::fetch_bytes(&version.dl_path, self.token.as_deref())
             .map(|data| (version.yanked, data))
     }
 
-    fn fetch_json<T>(&self, endpoint: &str) -> anyhow::Result<T>
+    fn fetch_json<T>(url: &str, token: Option<&str>) -> anyhow::Result<T>
     where
         for<'de> T: serde::Deserialize<'de>,
     {
-        let resp = attohttpc::get(format!("{}{}", self.host, endpoint))
-            .header("USER-AGENT", Self::get_user_agent())
-            .send()?;
-
-        anyhow::ensure!(
-            resp.status().is_success(),
-            "cannot fetch json for {}",
-            endpoint
-        );
+        let resp = Self::get(url, token)?.send()?;
+
+        anyhow::ensure!(resp.status().is_success(), "cannot fetch json for {}", url);
 
         resp.json()
-            .with_context(move || format!("cannot parse json from {}", endpoint))
+            .with_context(move || format!("cannot parse json from {}", url))
     }
 
-    fn fetch_bytes(&self, endpoint: &str) -> anyhow::Result<Vec<u8>> {
-        let resp = attohttpc::get(format!("{}{}", self.host, endpoint))
-            .header("USER-AGENT", Self::get_user_agent())
-            .send()?;
+    fn fetch_text(url: &str, token: Option<&str>) -> anyhow::Result<String> {
+        let resp = Self::get(url, token)?.send()?;
 
-        anyhow::ensure!(
-            resp.status().is_success(),
-            "cannot fetch bytes for {}",
-            endpoint
-        );
+        anyhow::ensure!(resp.status().is_success(), "cannot fetch {}", url);
+
+        resp.text()
+            .with_context(move || format!("cannot read text from {}", url))
+    }
+
+    fn fetch_bytes(url: &str, token: Option<&str>) -> anyhow::Result<Vec<u8>> {
+        let resp = Self::get(url, token)?.send()?;
+
+        anyhow::ensure!(resp.status().is_success(), "cannot fetch bytes for {}", url);
 
         let len = resp
             .headers()
@@ -129,6 +284,16 @@ impl Client {
         Ok(bytes)
     }
 
+    fn get(url: &str, token: Option<&str>) -> anyhow::Result<attohttpc::RequestBuilder> {
+        let req = attohttpc::get(url).header("USER-AGENT", Self::get_user_agent());
+        match token {
+            Some(token) => req
+                .try_header("AUTHORIZATION", token)
+                .with_context(|| "the registry token is not a valid header value"),
+            None => Ok(req),
+        }
+    }
+
     const fn get_user_agent() -> &'static str {
         concat!(
             env!("CARGO_PKG_NAME"),
@@ -154,34 +319,71 @@ pub struct Version {
     pub yanked: bool,
     /// The primary license of the crate
     pub license: Option<String>,
-    /// When the crate was created
-    #[serde(with = "time::serde::rfc3339")]
-    pub created_at: time::OffsetDateTime,
-
+    /// When the crate was created, if it is known
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_at: Option<time::OffsetDateTime>,
+    /// The minimum supported Rust version, if it declares one
+    #[serde(default)]
+    pub rust_version: Option<String>,
+    /// The name of every feature, including the implicit features for optional dependencies
+    #[serde(default)]
+    pub features: BTreeSet<String>,
+
+    #[serde(default)]
     dl_path: String,
 }
 
 impl Version {
+    /// Create a version from an index entry, this has no download path
+    pub(crate) fn from_index(entry: &IndexEntry) -> Self {
+        Self {
+            name: entry.name.clone(),
+            version: entry.vers.clone(),
+            yanked: entry.yanked,
+            license: None,
+            created_at: entry.pubtime,
+            rust_version: entry.rust_version.clone(),
+            features: Features::from_index(entry).features.into_keys().collect(),
+            dl_path: String::new(),
+        }
+    }
+
+    /// Whether this version builds with this rust version
+    ///
+    /// A version that doesn't declare a `rust_version` is assumed to build with any of them
+    pub fn supports_rust(&self, toolchain: &semver::Version) -> bool {
+        self.rust_version
+            .as_deref()
+            .and_then(crate::util::parse_rust_version)
+            .is_none_or(|required| required <= *toolchain)
+    }
+
+    /// Whether this version has this feature (or an optional dependency by this name)
+    pub fn has_feature(&self, name: &str) -> bool {
+        self.features.contains(name)
+    }
+
     const FMT: &'static [FormatItem<'static>] = time::macros::format_description!(
         "[year]-[month]-[day] [hour]:[minute]:[second] [offset_hour sign:mandatory][offset_minute]"
     );
 
-    pub fn format_verbose_time(&self) -> String {
-        self.created_at.format(&Self::FMT).expect("valid time")
+    pub fn format_verbose_time(&self) -> Option<String> {
+        self.created_at
+            .map(|created_at| created_at.format(&Self::FMT).expect("valid time"))
     }
 
-    pub fn format_approx_time_span(&self) -> String {
-        let d = time::OffsetDateTime::now_utc() - self.created_at;
+    pub fn format_approx_time_span(&self) -> Option<String> {
+        let d = time::OffsetDateTime::now_utc() - self.created_at?;
         macro_rules! try_time {
             ($($expr:tt => $class:expr)*) => {{
                 $(
                     match d.$expr() {
                         0 => {}
-                        1 => return format!("1 {} ago", $class),
-                        d => return format!("{} {}s ago", d, $class),
+                        1 => return Some(format!("1 {} ago", $class)),
+                        d => return Some(format!("{} {}s ago", d, $class)),
                     }
                 )*
-                String::from("just now")
+                Some(String::from("just now"))
             }};
         }
 
@@ -195,70 +397,22 @@ impl Version {
     }
 }
 
-pub mod json {
-    use crate::{features::Workspace, Version};
-
-    fn format_timestamp(time: &time::OffsetDateTime) -> String {
-        time.format(&Version::FMT).expect("valid time")
-    }
-
-    pub fn create_crates_from_versions(
-        name: &str,
-        versions: impl IntoIterator<Item = Version>,
-    ) -> serde_json::Value {
-        let array = versions.into_iter().map(|version| {
-            serde_json::json!({
-                "version": version.version,
-                "yanked": version.yanked,
-                "license": version.license,
-                "created_at": format_timestamp(&version.created_at),
-                "dl_path": version.dl_path,
-            })
-        });
-
-        serde_json::json!({
-            name: array.collect::<Vec<_>>()
-        })
-    }
-
-    pub fn create_crates_from_workspace<'a>(
-        workspace: &str,
-        crates: impl IntoIterator<Item = (&'a String, &'a String, bool)>,
-    ) -> serde_json::Value {
-        let array = crates.into_iter().map(|(name, version, published)| {
-            serde_json::json!({
-                "crate": name,
-                "version": version,
-                "published": published,
-            })
-        });
-
-        serde_json::json!({
-            workspace: array.collect::<Vec<_>>()
-        })
-    }
-
-    pub fn workspace(workspace: Workspace) -> serde_json::Value {
-        let map = workspace
-            .map
-            .into_iter()
-            .map(|(_, features)| {
-                serde_json::json!({
-                    "crate": features.name,
-                    "version": features.version,
-                    "published": features.published,
-                    "features": features.features,
-                    "dependencies": {
-                        "optional": features.optional_deps,
-                        "required": features.required_deps,
-                    }
-                })
-            })
-            .collect::<Vec<_>>();
-
-        serde_json::json!({
-            "workspace": workspace.hint,
-            "members": map
-        })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_uses_the_host() {
+        let client = Client::new("http://localhost/");
+        assert!(client.registry().is_crates_io());
+        assert_eq!(client.config.api.as_deref(), Some("http://localhost"));
+
+        let entry: IndexEntry =
+            serde_json::from_value(serde_json::json!({ "name": "serde", "vers": "1.0.0" }))
+                .unwrap();
+        assert_eq!(
+            client.config.download_url(&entry),
+            "http://localhost/api/v1/crates/serde/1.0.0/download"
+        );
     }
 }
@@ -0,0 +1,158 @@
+use crate::cargo_config::{env_key, CargoConfig, ProviderSpec, RegistryConfig};
+use anyhow::Context as _;
+use std::{
+    io::{BufRead as _, BufReader, Write as _},
+    process::{Command, Stdio},
+};
+
+/// Find the token for this registry, the same way cargo does
+///
+/// This tries each of the registry's credential providers in turn (by default, only `cargo:token`).
+///
+/// Supported providers are:
+/// * `cargo:token` -- `CARGO_REGISTRIES_<NAME>_TOKEN`, then the `token` from the cargo config or `credentials.toml`
+/// * `cargo:token-from-stdout <cmd>` -- runs the command and uses its output
+/// * any external provider speaking the credential provider protocol
+pub fn find_token(registry: &RegistryConfig) -> anyhow::Result<Option<String>> {
+    let mut config = CargoConfig::load()?;
+    let table = config.registries.remove(&registry.name).unwrap_or_default();
+
+    let providers = match table.credential_provider {
+        Some(provider) => vec![provider.to_args()],
+        None => config
+            .registry
+            .global_credential_providers
+            .unwrap_or_else(|| vec![String::from("cargo:token")])
+            .into_iter()
+            .rev() // the later providers take precedence
+            .map(|provider| ProviderSpec::String(provider).to_args())
+            .collect(),
+    };
+
+    for provider in providers {
+        let (program, args) = match provider.split_first() {
+            Some((program, args)) => (program.as_str(), args),
+            None => continue,
+        };
+
+        let token = match program {
+            "cargo:token" => std::env::var(env_key(&registry.name, "TOKEN"))
+                .ok()
+                .or_else(|| table.token.clone()),
+            "cargo:token-from-stdout" => Some(token_from_stdout(args)?),
+            // the platform keychains aren't supported
+            s if s.starts_with("cargo:") => None,
+            _ => token_from_provider(registry, program, args)?,
+        };
+
+        if let Some(token) = token.filter(|s| !s.is_empty()) {
+            return Ok(Some(token));
+        }
+    }
+
+    Ok(None)
+}
+
+fn token_from_stdout(args: &[String]) -> anyhow::Result<String> {
+    let (program, args) = args
+        .split_first()
+        .with_context(|| "`cargo:token-from-stdout` requires a command")?;
+
+    let output = Command::new(program)
+        .args(args)
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| anyhow::anyhow!("cannot run credential command: {}", program))?;
+
+    anyhow::ensure!(
+        output.status.success(),
+        "credential command '{}' failed: {}",
+        program,
+        output.status
+    );
+
+    let token = String::from_utf8(output.stdout)?;
+    Ok(token.trim().to_string())
+}
+
+/// Run an external credential provider (see the `credential-provider` section of the cargo book)
+fn token_from_provider(
+    registry: &RegistryConfig,
+    program: &str,
+    args: &[String],
+) -> anyhow::Result<Option<String>> {
+    #[derive(serde::Deserialize)]
+    struct Hello {
+        v: Vec<u32>,
+    }
+
+    #[derive(serde::Deserialize)]
+    enum Response {
+        Ok {
+            token: String,
+        },
+        Err {
+            kind: String,
+            message: Option<String>,
+        },
+    }
+
+    // the args are only passed in the request, cargo doesn't put them on the command line
+    let mut child = Command::new(program)
+        .arg("--cargo-plugin")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| anyhow::anyhow!("cannot run credential provider: {}", program))?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+
+    let mut line = String::new();
+    stdout.read_line(&mut line)?;
+    let hello: Hello = serde_json::from_str(&line)
+        .with_context(|| anyhow::anyhow!("invalid hello from credential provider: {}", program))?;
+    anyhow::ensure!(
+        hello.v.contains(&1),
+        "credential provider '{}' does not support version 1 of the protocol",
+        program
+    );
+
+    let request = serde_json::json!({
+        "v": 1,
+        "registry": {
+            "index-url": format!("sparse+{}/", registry.index),
+            "name": registry.name,
+        },
+        "kind": "get",
+        "operation": "read",
+        "args": args,
+    });
+    writeln!(stdin, "{}", request)?;
+    stdin.flush()?;
+
+    line.clear();
+    stdout.read_line(&mut line)?;
+
+    // closing stdin lets the provider exit
+    drop(stdin);
+    child.wait()?;
+
+    let response = serde_json::from_str(&line).with_context(|| {
+        anyhow::anyhow!("invalid response from credential provider: {}", program)
+    })?;
+
+    match response {
+        Response::Ok { token } => Ok(Some(token)),
+        Response::Err { kind, .. } if kind == "not-found" || kind == "url-not-supported" => {
+            Ok(None)
+        }
+        Response::Err { kind, message } => anyhow::bail!(
+            "credential provider '{}' failed ({}): {}",
+            program,
+            kind,
+            message.as_deref().unwrap_or("no message")
+        ),
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{features::Features, resolve::Resolver};
+
+/// A dependency line for a `Cargo.toml`, with a chosen set of features
+#[derive(Debug, Clone)]
+pub struct DependencyLine {
+    /// The name of the crate
+    pub name: String,
+    /// The version of the crate
+    pub version: String,
+    /// Whether the default features are enabled
+    pub default_features: bool,
+    /// The features to enable
+    pub features: Vec<String>,
+    /// The features that are already enabled by another one, and what enables them
+    pub implied: Vec<(String, String)>,
+}
+
+impl DependencyLine {
+    /// Create a dependency line for this crate with these features
+    ///
+    /// Every feature has to exist on the crate. `default` can be provided to keep the default features.
+    pub fn new<I, S>(features: &Features, enable: I, default_features: bool) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut this = Self {
+            name: features.name.clone(),
+            version: features.version.clone(),
+            default_features,
+            features: vec![],
+            implied: vec![],
+        };
+
+        for name in enable {
+            let name = name.as_ref();
+            if name == "default" {
+                this.default_features = true;
+                continue;
+            }
+
+            if !features.features.contains_key(name) {
+                let mut available = features
+                    .features
+                    .keys()
+                    .filter(|name| *name != "default")
+                    .map(String::as_str)
+                    .collect::<Vec<_>>();
+                available.sort_unstable();
+
+                anyhow::bail!(
+                    "{} = {} has no feature named '{}'. the features are: {}",
+                    features.name,
+                    features.version,
+                    name,
+                    available.join(", ")
+                )
+            }
+
+            if !this.features.iter().any(|feature| feature == name) {
+                this.features.push(name.to_string())
+            }
+        }
+
+        this.implied = this.find_implied(features)?;
+        Ok(this)
+    }
+
+    /// Find the chosen features that are already enabled by another chosen feature (or by `default`)
+    fn find_implied(&self, features: &Features) -> anyhow::Result<Vec<(String, String)>> {
+        let mut sources = self.features.iter().map(String::as_str).collect::<Vec<_>>();
+        if self.default_features && features.features.contains_key("default") {
+            sources.push("default")
+        }
+
+        let mut implied = vec![];
+        for source in sources {
+            let resolved = Resolver::new(features).resolve(std::iter::once(source))?;
+            implied.extend(
+                self.features
+                    .iter()
+                    .filter(|name| *name != source && resolved.features.contains(*name))
+                    .filter(|name| !implied.iter().any(|(implied, _)| implied == *name))
+                    .map(|name| (name.clone(), source.to_string()))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        Ok(implied)
+    }
+}
+
+impl std::fmt::Display for DependencyLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let quote = |s: &str| toml::Value::String(s.to_string()).to_string();
+
+        if self.default_features && self.features.is_empty() {
+            return write!(f, "{} = {}", self.name, quote(&self.version));
+        }
+
+        write!(f, "{} = {{ version = {}", self.name, quote(&self.version))?;
+        if !self.default_features {
+            write!(f, ", default-features = false")?;
+        }
+        if !self.features.is_empty() {
+            let features = self
+                .features
+                .iter()
+                .map(|name| quote(name))
+                .collect::<Vec<_>>();
+            write!(f, ", features = [{}]", features.join(", "))?;
+        }
+        write!(f, " }}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features() -> Features {
+        Features::from_json(serde_json::json!({
+            "name": "x",
+            "vers": "1.2.3",
+            "features": {
+                "default": ["std"],
+                "std": ["alloc"],
+                "alloc": [],
+                "derive": [],
+                "full": ["derive", "std"],
+            },
+        }))
+    }
+
+    fn line(enable: &[&str], default_features: bool) -> DependencyLine {
+        DependencyLine::new(&features(), enable, default_features).unwrap()
+    }
+
+    fn implied(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter()
+            .map(|(name, by)| (name.to_string(), by.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn defaults_only() {
+        let line = line(&[], true);
+        assert_eq!(line.to_string(), r#"x = "1.2.3""#);
+        assert!(line.implied.is_empty());
+    }
+
+    #[test]
+    fn without_default_features() {
+        assert_eq!(
+            line(&[], false).to_string(),
+            r#"x = { version = "1.2.3", default-features = false }"#
+        );
+        assert_eq!(
+            line(&["derive", "alloc", "derive"], false).to_string(),
+            r#"x = { version = "1.2.3", default-features = false, features = ["derive", "alloc"] }"#
+        );
+    }
+
+    #[test]
+    fn default_in_enable() {
+        let line = line(&["default", "derive"], false);
+        assert!(line.default_features);
+        assert_eq!(
+            line.to_string(),
+            r#"x = { version = "1.2.3", features = ["derive"] }"#
+        );
+    }
+
+    #[test]
+    fn implied_features() {
+        // `default` enables `std`, which enables `alloc`
+        let with_default = line(&["alloc", "std", "derive"], true);
+        assert_eq!(
+            with_default.to_string(),
+            r#"x = { version = "1.2.3", features = ["alloc", "std", "derive"] }"#
+        );
+        assert_eq!(
+            with_default.implied,
+            implied(&[("alloc", "std"), ("std", "default")])
+        );
+
+        let without_default = line(&["derive", "full"], false);
+        assert_eq!(without_default.implied, implied(&[("derive", "full")]));
+    }
+
+    #[test]
+    fn unknown_feature() {
+        let err = DependencyLine::new(&features(), ["nope"], true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "x = 1.2.3 has no feature named 'nope'. the features are: alloc, derive, full, std"
+        );
+    }
+}
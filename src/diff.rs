@@ -0,0 +1,405 @@
+use crate::features::{Dependency, FeatureEntry, Features, Kind};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The changes to a crate's features and dependencies between two versions
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FeatureDiff {
+    /// The name of the crate
+    pub name: String,
+    /// The version being compared from
+    pub old_version: String,
+    /// The version being compared to
+    pub new_version: String,
+    /// Features that only exist in the new version
+    pub added_features: BTreeSet<String>,
+    /// Features that only exist in the old version
+    pub removed_features: BTreeSet<String>,
+    /// Features that were removed and added back under a different name, with the same entries
+    pub renamed_features: Vec<Renamed>,
+    /// Changes to the `default` feature
+    pub default: EntryDiff,
+    /// Changes to what each feature (that is in both versions) enables
+    pub changed_features: BTreeMap<String, EntryDiff>,
+    /// Changes to the dependencies, for each kind of dependency
+    pub dependencies: BTreeMap<Kind, DependencyDiff>,
+}
+
+/// A feature that was renamed
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Renamed {
+    /// The name in the old version
+    pub from: String,
+    /// The name in the new version
+    pub to: String,
+}
+
+/// The entries that were added to and removed from a feature
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EntryDiff {
+    /// Entries only in the new version
+    pub added: BTreeSet<FeatureEntry>,
+    /// Entries only in the old version
+    pub removed: BTreeSet<FeatureEntry>,
+}
+
+impl EntryDiff {
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    fn new<'a>(
+        old: impl IntoIterator<Item = &'a FeatureEntry>,
+        new: impl IntoIterator<Item = &'a FeatureEntry>,
+    ) -> Self {
+        let old = old.into_iter().cloned().collect::<BTreeSet<_>>();
+        let new = new.into_iter().cloned().collect::<BTreeSet<_>>();
+        Self {
+            added: new.difference(&old).cloned().collect(),
+            removed: old.difference(&new).cloned().collect(),
+        }
+    }
+}
+
+/// The dependencies that changed, for a kind of dependency
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DependencyDiff {
+    /// Dependencies only in the new version
+    pub added: Vec<Dependency>,
+    /// Dependencies only in the old version
+    pub removed: Vec<Dependency>,
+    /// Dependencies whose requirement changed, or that became (or stopped being) optional
+    pub changed: Vec<DependencyChange>,
+}
+
+impl DependencyDiff {
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A dependency that is in both versions, but differs
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DependencyChange {
+    /// The name the crate refers to the dependency as
+    pub name: String,
+    /// Which target, if any, that this dependency is required for
+    pub target: Option<String>,
+    /// The requirement in the old version
+    pub old_req: String,
+    /// The requirement in the new version
+    pub new_req: String,
+    /// Whether it was optional in the old version
+    pub old_optional: bool,
+    /// Whether it is optional in the new version
+    pub new_optional: bool,
+}
+
+impl FeatureDiff {
+    /// Compare the features and dependencies of two versions of a crate
+    pub fn new(old: &Features, new: &Features) -> Self {
+        let (old_names, new_names) = (feature_names(old), feature_names(new));
+
+        let mut added_features = &new_names - &old_names;
+        let mut removed_features = &old_names - &new_names;
+
+        // a feature was probably renamed if another one appeared with exactly the same entries
+        let mut renamed_features = vec![];
+        for from in removed_features.clone() {
+            let entries = entry_set(&old.features[&from]);
+            let to = added_features
+                .iter()
+                .find(|to| !entries.is_empty() && entry_set(&new.features[*to]) == entries);
+
+            if let Some(to) = to.cloned() {
+                removed_features.remove(&from);
+                added_features.remove(&to);
+                renamed_features.push(Renamed { from, to });
+            }
+        }
+
+        let entries = |features: &'_ Features, name: &str| {
+            features.features.get(name).cloned().unwrap_or_default()
+        };
+
+        let default = EntryDiff::new(&entries(old, "default"), &entries(new, "default"));
+
+        let changed_features = old_names
+            .intersection(&new_names)
+            .map(|name| {
+                let diff = EntryDiff::new(&old.features[name], &new.features[name]);
+                (name.clone(), diff)
+            })
+            .filter(|(_, diff)| !diff.is_empty())
+            .collect();
+
+        let dependencies = [Kind::Normal, Kind::Development, Kind::Build]
+            .iter()
+            .map(|&kind| (kind, diff_dependencies(old, new, kind)))
+            .filter(|(_, diff)| !diff.is_empty())
+            .collect();
+
+        Self {
+            name: new.name.clone(),
+            old_version: old.version.clone(),
+            new_version: new.version.clone(),
+            added_features,
+            removed_features,
+            renamed_features,
+            default,
+            changed_features,
+            dependencies,
+        }
+    }
+
+    /// Whether nothing changed between the versions
+    pub fn is_empty(&self) -> bool {
+        self.added_features.is_empty()
+            && self.removed_features.is_empty()
+            && self.renamed_features.is_empty()
+            && self.default.is_empty()
+            && self.changed_features.is_empty()
+            && self.dependencies.is_empty()
+    }
+}
+
+fn entry_set(entries: &[FeatureEntry]) -> BTreeSet<&FeatureEntry> {
+    entries.iter().collect()
+}
+
+/// All of the features, other than `default`
+fn feature_names(features: &Features) -> BTreeSet<String> {
+    features
+        .features
+        .keys()
+        .filter(|name| *name != "default")
+        .cloned()
+        .collect()
+}
+
+type DependencyMap<'a> = BTreeMap<(String, Option<String>), (&'a Dependency, bool)>;
+
+/// The dependencies of this kind, by their name and target, along with whether they are optional
+fn collect_dependencies(features: &Features, kind: Kind) -> DependencyMap<'_> {
+    let optional = features.optional_deps.get(&kind).into_iter().flatten();
+    let required = features.required_deps.get(&kind).into_iter().flatten();
+    optional
+        .map(|dep| (dep, true))
+        .chain(required.map(|dep| (dep, false)))
+        .map(|(dep, optional)| ((dep.key().to_string(), dep.target.clone()), (dep, optional)))
+        .collect()
+}
+
+fn diff_dependencies(old: &Features, new: &Features, kind: Kind) -> DependencyDiff {
+    let (old, new) = (
+        collect_dependencies(old, kind),
+        collect_dependencies(new, kind),
+    );
+    let mut diff = DependencyDiff::default();
+
+    for (key, (dep, _)) in &new {
+        if !old.contains_key(key) {
+            diff.added.push((*dep).clone())
+        }
+    }
+
+    for (key, (old_dep, old_optional)) in &old {
+        let (new_dep, new_optional) = match new.get(key) {
+            Some(new) => *new,
+            None => {
+                diff.removed.push((*old_dep).clone());
+                continue;
+            }
+        };
+
+        if old_dep.req != new_dep.req || *old_optional != new_optional {
+            diff.changed.push(DependencyChange {
+                name: key.0.clone(),
+                target: key.1.clone(),
+                old_req: old_dep.req.clone(),
+                new_req: new_dep.req.clone(),
+                old_optional: *old_optional,
+                new_optional,
+            })
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(old: serde_json::Value, new: serde_json::Value) -> FeatureDiff {
+        let (old, new) = (Features::from_json(old), Features::from_json(new));
+        FeatureDiff::new(&old, &new)
+    }
+
+    fn set(list: &[&str]) -> BTreeSet<String> {
+        list.iter().map(ToString::to_string).collect()
+    }
+
+    fn entries(list: &[&str]) -> BTreeSet<FeatureEntry> {
+        list.iter()
+            .map(|entry| FeatureEntry::parse(entry))
+            .collect()
+    }
+
+    #[test]
+    fn unchanged() {
+        let features = serde_json::json!({
+            "features": { "default": ["std"], "std": [] },
+            "deps": [{ "name": "serde", "req": "1" }],
+        });
+        assert!(diff(features.clone(), features).is_empty());
+    }
+
+    #[test]
+    fn added_removed_and_renamed() {
+        let diff = diff(
+            serde_json::json!({
+                "features": {
+                    "std": [],
+                    "old_name": ["std"],
+                    "gone": ["std"],
+                    "empty": [],
+                },
+            }),
+            serde_json::json!({
+                "features": {
+                    "std": [],
+                    "new_name": ["std"],
+                    "new": ["alloc"],
+                    "alloc": [],
+                    "other_empty": [],
+                },
+            }),
+        );
+
+        // `gone` and `old_name` have the same entries, only one of them can be matched
+        assert_eq!(diff.renamed_features.len(), 1);
+        assert_eq!(diff.renamed_features[0].to, "new_name");
+        let from = diff.renamed_features[0].from.as_str();
+        assert!(from == "gone" || from == "old_name");
+
+        // features without entries aren't matched, they'd match anything
+        let mut removed = set(&["gone", "old_name", "empty"]);
+        removed.remove(from);
+        assert_eq!(diff.removed_features, removed);
+        assert_eq!(diff.added_features, set(&["new", "alloc", "other_empty"]));
+        assert!(diff.changed_features.is_empty());
+    }
+
+    #[test]
+    fn default_features() {
+        let diff = diff(
+            serde_json::json!({ "features": { "default": ["std", "derive"], "std": [], "derive": [] } }),
+            serde_json::json!({ "features": { "default": ["std", "alloc"], "std": [], "derive": [], "alloc": [] } }),
+        );
+        assert_eq!(diff.default.added, entries(&["alloc"]));
+        assert_eq!(diff.default.removed, entries(&["derive"]));
+        // default isn't a feature of its own
+        assert!(!diff.changed_features.contains_key("default"));
+        assert_eq!(diff.added_features, set(&["alloc"]));
+
+        let diff = self::diff(
+            serde_json::json!({ "features": { "std": [] } }),
+            serde_json::json!({ "features": { "default": ["std"], "std": [] } }),
+        );
+        assert_eq!(diff.default.added, entries(&["std"]));
+        assert!(diff.default.removed.is_empty());
+        assert!(diff.added_features.is_empty());
+    }
+
+    #[test]
+    fn changed_features() {
+        let diff = diff(
+            serde_json::json!({
+                "features": { "std": ["serde/std"], "derive": ["serde/derive"] },
+                "deps": [{ "name": "serde", "req": "1", "optional": true }],
+            }),
+            serde_json::json!({
+                "features": { "std": ["serde?/std", "alloc"], "derive": ["serde/derive"], "alloc": [] },
+                "deps": [{ "name": "serde", "req": "1", "optional": true }],
+            }),
+        );
+        assert_eq!(diff.changed_features.keys().collect::<Vec<_>>(), ["std"]);
+        assert_eq!(
+            diff.changed_features["std"].added,
+            entries(&["serde?/std", "alloc"])
+        );
+        assert_eq!(
+            diff.changed_features["std"].removed,
+            entries(&["serde/std"])
+        );
+    }
+
+    #[test]
+    fn dependencies() {
+        let diff = diff(
+            serde_json::json!({
+                "deps": [
+                    { "name": "serde", "req": "^1.0.100" },
+                    { "name": "log", "req": "0.4", "optional": true },
+                    { "name": "winapi", "req": "0.3", "target": "cfg(windows)" },
+                    { "name": "old", "req": "1" },
+                    { "name": "cc", "req": "1", "kind": "build" },
+                    { "name": "proptest", "req": "1", "kind": "dev" },
+                ],
+            }),
+            serde_json::json!({
+                "deps": [
+                    { "name": "serde", "req": "^1.0.200" },
+                    { "name": "log", "req": "0.4" },
+                    { "name": "winapi", "req": "0.3", "target": "cfg(windows)" },
+                    { "name": "windows-sys", "req": "0.52", "target": "cfg(windows)" },
+                    { "name": "cc", "req": "1", "kind": "build" },
+                    { "name": "proptest", "req": "1.4", "kind": "dev" },
+                ],
+            }),
+        );
+
+        let normal = &diff.dependencies[&Kind::Normal];
+        let names =
+            |deps: &[Dependency]| deps.iter().map(|dep| dep.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&normal.added), ["windows-sys"]);
+        assert_eq!(names(&normal.removed), ["old"]);
+
+        let changed = normal
+            .changed
+            .iter()
+            .map(|change| {
+                let DependencyChange {
+                    name,
+                    old_req,
+                    new_req,
+                    old_optional,
+                    new_optional,
+                    ..
+                } = change;
+                (
+                    name.as_str(),
+                    old_req.as_str(),
+                    new_req.as_str(),
+                    *old_optional,
+                    *new_optional,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changed,
+            [
+                ("log", "0.4", "0.4", true, false),
+                ("serde", "^1.0.100", "^1.0.200", false, false),
+            ]
+        );
+
+        assert_eq!(
+            diff.dependencies[&Kind::Development].changed[0].new_req,
+            "1.4"
+        );
+        assert!(!diff.dependencies.contains_key(&Kind::Build));
+    }
+}
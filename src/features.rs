@@ -1,5 +1,9 @@
+use crate::{index::IndexEntry, resolve::Resolver, target::Target};
 use cargo_metadata::{DependencyKind, Metadata, Package, PackageId};
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug, Default, serde::Serialize)]
 pub struct Workspace {
@@ -23,9 +27,27 @@ impl Workspace {
         }
     }
 
+    pub(crate) fn from_index(entry: &IndexEntry, registry: &str) -> Self {
+        let id = PackageId {
+            repr: format!("{} {} (sparse+{})", entry.name, entry.vers, registry),
+        };
+
+        Self {
+            hint: entry.name.clone(),
+            map: std::iter::once((id, Features::from_index(entry))).collect(),
+        }
+    }
+
     pub fn is_workspace(&self) -> bool {
         self.map.len() > 1
     }
+
+    /// Remove the target-specific dependencies that don't apply to this target, from every crate
+    pub fn retain_target(&mut self, target: &Target) {
+        for features in self.map.values_mut() {
+            features.retain_target(target)
+        }
+    }
 }
 
 /// A feature mapping
@@ -39,23 +61,77 @@ pub struct Features {
     // TODO list /which/ registry it was published to
     pub published: bool,
     /// Feature map
-    pub features: HashMap<String, Vec<String>>,
+    pub features: HashMap<String, Vec<FeatureEntry>>,
+    /// The features that cargo implicitly created for optional dependencies
+    pub implicit_features: BTreeSet<String>,
+    /// The optional dependencies that have no implicit feature, because they were referred to with `dep:`
+    pub hidden_deps: BTreeSet<String>,
+    /// The documentation for each feature, from the `## ` comments above it in the `Cargo.toml`
+    pub docs: HashMap<String, String>,
+    /// How docs.rs builds the crate, from `[package.metadata.docs.rs]`
+    pub docs_rs: Option<DocsRs>,
+    /// The minimum supported Rust version (`rust-version`)
+    pub rust_version: Option<String>,
+    /// The Rust edition
+    pub edition: Option<String>,
+    /// The license (an SPDX expression)
+    pub license: Option<String>,
+    /// The native library this crate links to
+    pub links: Option<String>,
+    /// The description of the crate
+    pub description: Option<String>,
+    /// The URL of the source repository
+    pub repository: Option<String>,
+    /// The URL of the homepage
+    pub homepage: Option<String>,
+    /// The crates.io categories
+    pub categories: Vec<String>,
     /// Optional deps. map
     pub optional_deps: HashMap<Kind, Vec<Dependency>>,
     /// Required deps. map
     pub required_deps: HashMap<Kind, Vec<Dependency>>,
+    /// The path to the crate's `Cargo.toml`, if it is on disk
+    pub manifest_path: Option<PathBuf>,
 }
 
 impl Features {
+    /// Remove the target-specific dependencies that don't apply to this target
+    pub fn retain_target(&mut self, target: &Target) {
+        let deps = self.optional_deps.values_mut();
+        for deps in deps.chain(self.required_deps.values_mut()) {
+            deps.retain(|dep| dep.target.as_deref().is_none_or(|s| target.matches(s)))
+        }
+    }
+
+    /// The features that aren't enabled when docs.rs builds the crate
+    ///
+    /// This is `None` if the crate has no docs.rs configuration
+    pub fn undocumented_features(&self) -> Option<BTreeSet<String>> {
+        let covered = self.docs_rs.as_ref()?.covered_features(self);
+        let features = self.features.keys();
+        Some(
+            features
+                .filter(|name| !covered.contains(*name))
+                .cloned()
+                .collect(),
+        )
+    }
+
     // TODO this should just take a Package and parse it
     pub(crate) fn parse(pkg: Package) -> Self {
+        let manifest_path = Some(pkg.manifest_path.clone().into_std_path_buf());
+        let docs_rs = pkg
+            .metadata
+            .get("docs")
+            .and_then(|docs| docs.get("rs"))
+            .and_then(DocsRs::from_metadata);
         let (mut name, mut version) = (None, None);
         let (mut features, mut optional_deps, mut required_deps) =
             (HashMap::new(), HashMap::new(), HashMap::new());
 
         name.get_or_insert_with(|| pkg.name.clone()); // why
         version.get_or_insert_with(|| pkg.version.to_string());
-        features.extend(pkg.features);
+        features.extend(parse_feature_map(pkg.features));
 
         for dep in pkg.dependencies {
             let key = dep.kind.into();
@@ -80,19 +156,379 @@ impl Features {
             _ => true,
         };
 
+        let (implicit_features, hidden_deps) = add_implicit_features(&mut features, &optional_deps);
+
+        // cargo_metadata drops comments, so read them from the manifest itself
+        let docs = manifest_path
+            .as_deref()
+            .map(|path| parse_feature_docs(path, &features))
+            .unwrap_or_default();
+
         Self {
             name: name.unwrap(),
             version: version.unwrap(),
             published,
             features,
+            implicit_features,
+            hidden_deps,
+            docs,
+            docs_rs,
+            rust_version: pkg.rust_version.map(|v| v.to_string()),
+            edition: Some(pkg.edition.to_string()),
+            license: pkg.license,
+            links: pkg.links,
+            description: pkg.description,
+            repository: pkg.repository,
+            homepage: pkg.homepage,
+            categories: pkg.categories,
             optional_deps,
             required_deps,
+            manifest_path,
+        }
+    }
+
+    pub(crate) fn from_index(entry: &IndexEntry) -> Self {
+        let mut features = parse_feature_map(entry.all_features());
+        let (mut optional_deps, mut required_deps) = (HashMap::new(), HashMap::new());
+
+        for dep in &entry.deps {
+            let value = Dependency {
+                name: dep.package.clone().unwrap_or_else(|| dep.name.clone()),
+                req: dep.req.clone(),
+                target: dep.target.clone(),
+                rename: dep.package.as_ref().map(|_| dep.name.clone()),
+                features: dep.features.clone(),
+            };
+
+            let map: &mut HashMap<Kind, Vec<Dependency>> = if dep.optional {
+                &mut optional_deps
+            } else {
+                &mut required_deps
+            };
+            map.entry(dep.kind()).or_default().push(value)
+        }
+
+        let (implicit_features, hidden_deps) = add_implicit_features(&mut features, &optional_deps);
+
+        Self {
+            name: entry.name.clone(),
+            version: entry.vers.clone(),
+            published: true,
+            features,
+            implicit_features,
+            hidden_deps,
+            docs: HashMap::new(),
+            docs_rs: None,
+            links: entry.links.clone(),
+            rust_version: entry.rust_version.clone(),
+            optional_deps,
+            required_deps,
+            manifest_path: None,
+            ..Self::default()
+        }
+    }
+}
+
+/// An entry in a feature's list
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FeatureEntry {
+    /// Enables another feature (`name`)
+    ///
+    /// For older crates, this can also be an optional dependency
+    Feature {
+        /// The name of the feature
+        name: String,
+    },
+    /// Activates an optional dependency, without enabling a feature (`dep:name`)
+    Dependency {
+        /// The name the crate refers to the dependency as
+        name: String,
+    },
+    /// Enables a feature on a dependency, activating it if its optional (`name/feature`)
+    DependencyFeature {
+        /// The name the crate refers to the dependency as
+        dep: String,
+        /// The feature to enable on the dependency
+        feature: String,
+    },
+    /// Enables a feature on a dependency, only if something else activates it (`name?/feature`)
+    WeakDependencyFeature {
+        /// The name the crate refers to the dependency as
+        dep: String,
+        /// The feature to enable on the dependency
+        feature: String,
+    },
+}
+
+impl FeatureEntry {
+    /// Parse an entry from a feature's list
+    pub fn parse(entry: &str) -> Self {
+        if let Some(name) = entry.strip_prefix("dep:") {
+            return Self::Dependency {
+                name: name.to_string(),
+            };
+        }
+
+        match entry.split_once('/') {
+            Some((dep, feature)) => match dep.strip_suffix('?') {
+                Some(dep) => Self::WeakDependencyFeature {
+                    dep: dep.to_string(),
+                    feature: feature.to_string(),
+                },
+                None => Self::DependencyFeature {
+                    dep: dep.to_string(),
+                    feature: feature.to_string(),
+                },
+            },
+            None => Self::Feature {
+                name: entry.to_string(),
+            },
+        }
+    }
+
+    /// The name of the dependency this entry refers to, if it refers to one
+    pub fn dependency(&self) -> Option<&str> {
+        match self {
+            Self::Feature { .. } => None,
+            Self::Dependency { name } => Some(name),
+            Self::DependencyFeature { dep, .. } | Self::WeakDependencyFeature { dep, .. } => {
+                Some(dep)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for FeatureEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Feature { name } => write!(f, "{}", name),
+            Self::Dependency { name } => write!(f, "dep:{}", name),
+            Self::DependencyFeature { dep, feature } => write!(f, "{}/{}", dep, feature),
+            Self::WeakDependencyFeature { dep, feature } => write!(f, "{}?/{}", dep, feature),
+        }
+    }
+}
+
+fn parse_feature_map(
+    features: impl IntoIterator<Item = (String, Vec<String>)>,
+) -> HashMap<String, Vec<FeatureEntry>> {
+    features
+        .into_iter()
+        .map(|(name, entries)| {
+            let entries = entries.iter().map(|s| FeatureEntry::parse(s)).collect();
+            (name, entries)
+        })
+        .collect()
+}
+
+/// Finds the `## ` doc comments for each feature (and each optional dependency with an implicit feature)
+///
+/// This follows the `document-features` convention. `#! ` comments aren't attached to a feature, so they are skipped.
+///
+/// A published crate has its comments stripped from its `Cargo.toml`, so this prefers the `Cargo.toml.orig` next to it
+fn parse_feature_docs(
+    manifest_path: &Path,
+    features: &HashMap<String, Vec<FeatureEntry>>,
+) -> HashMap<String, String> {
+    let orig = manifest_path.with_extension("toml.orig");
+    let path = if orig.is_file() {
+        &*orig
+    } else {
+        manifest_path
+    };
+
+    let document = match std::fs::read_to_string(path)
+        .ok()
+        .and_then(|data| data.parse::<toml_edit::DocumentMut>().ok())
+    {
+        Some(document) => document,
+        None => return HashMap::new(),
+    };
+
+    fn find_docs(table: &dyn toml_edit::TableLike, docs: &mut HashMap<String, String>) {
+        for (key, _) in table.iter() {
+            let prefix = table
+                .key(key)
+                .and_then(|key| key.leaf_decor().prefix())
+                .and_then(|prefix| prefix.as_str());
+            if let Some(doc) = prefix.and_then(doc_comment) {
+                docs.entry(key.to_string()).or_insert(doc);
+            }
         }
     }
+
+    let mut docs = HashMap::new();
+    if let Some(table) = document
+        .get("features")
+        .and_then(|item| item.as_table_like())
+    {
+        find_docs(table, &mut docs);
+    }
+
+    // optional dependencies can be documented in their tables
+    let targets = document
+        .get("target")
+        .and_then(|item| item.as_table_like())
+        .into_iter()
+        .flat_map(|targets| targets.iter())
+        .filter_map(|(_, item)| item.as_table_like());
+
+    for table in std::iter::once(document.as_table() as &dyn toml_edit::TableLike).chain(targets) {
+        for kind in ["dependencies", "build-dependencies", "dev-dependencies"] {
+            if let Some(table) = table.get(kind).and_then(|item| item.as_table_like()) {
+                find_docs(table, &mut docs);
+            }
+        }
+    }
+
+    docs.retain(|name, _| features.contains_key(name));
+    docs
+}
+
+/// Takes the `## ` lines directly above a key, from its decor
+fn doc_comment(prefix: &str) -> Option<String> {
+    let mut lines = prefix
+        .lines()
+        .map(str::trim)
+        .rev()
+        .take_while(|line| line.starts_with("##"))
+        .map(|line| {
+            let line = &line[2..];
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        })
+        .collect::<Vec<_>>();
+    lines.reverse();
+
+    let doc = lines.join("\n");
+    let doc = doc.trim_matches('\n');
+    (!doc.is_empty()).then(|| doc.to_string())
+}
+
+/// Cargo creates an implicit feature for each optional dependency, unless its referred to with `dep:`
+///
+/// This adds any that are missing, and returns the implicit features and the optional dependencies without one
+fn add_implicit_features(
+    features: &mut HashMap<String, Vec<FeatureEntry>>,
+    optional_deps: &HashMap<Kind, Vec<Dependency>>,
+) -> (BTreeSet<String>, BTreeSet<String>) {
+    let (mut implicit, mut hidden) = (BTreeSet::new(), BTreeSet::new());
+
+    for name in optional_deps.values().flatten().map(Dependency::key) {
+        let entry = FeatureEntry::Dependency {
+            name: name.to_string(),
+        };
+        let is_implicit = |feature: &str, entries: &[FeatureEntry]| {
+            feature == name && entries.len() == 1 && entries[0] == entry
+        };
+
+        let used = features
+            .iter()
+            .any(|(feature, entries)| !is_implicit(feature, entries) && entries.contains(&entry));
+
+        if used {
+            hidden.insert(name.to_string());
+            continue;
+        }
+
+        match features.get(name) {
+            Some(entries) if !is_implicit(name, entries) => continue,
+            Some(..) => {}
+            None => {
+                features.insert(name.to_string(), vec![entry.clone()]);
+            }
+        }
+        implicit.insert(name.to_string());
+    }
+
+    (implicit, hidden)
+}
+
+/// The configuration docs.rs uses to build a crate's documentation
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all(deserialize = "kebab-case"))]
+pub struct DocsRs {
+    /// The features that are enabled
+    pub features: Vec<String>,
+    /// Whether every feature is enabled
+    pub all_features: bool,
+    /// Whether the default features are disabled
+    pub no_default_features: bool,
+    /// The target that is shown first
+    pub default_target: Option<String>,
+    /// Every target that is built, if it isn't docs.rs's default set
+    pub targets: Option<Vec<String>>,
+    /// Extra arguments passed to rustc
+    pub rustc_args: Vec<String>,
+    /// Extra arguments passed to rustdoc
+    pub rustdoc_args: Vec<String>,
+    /// Extra arguments passed to cargo
+    pub cargo_args: Vec<String>,
+}
+
+impl DocsRs {
+    /// Read the `[package.metadata.docs.rs]` table
+    ///
+    /// A field with an unexpected type is ignored, rather than the whole table
+    pub fn from_metadata(config: &serde_json::Value) -> Option<Self> {
+        let config = config.as_object()?;
+        let flag = |key| {
+            config
+                .get(key)
+                .and_then(|v| v.as_bool())
+                .unwrap_or_default()
+        };
+        let string = |key| config.get(key).and_then(|v| v.as_str()).map(String::from);
+        let strings = |key| {
+            config.get(key).and_then(|v| v.as_array()).map(|list| {
+                list.iter()
+                    .filter_map(|v| v.as_str())
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        Some(Self {
+            features: strings("features").unwrap_or_default(),
+            all_features: flag("all-features"),
+            no_default_features: flag("no-default-features"),
+            default_target: string("default-target"),
+            targets: strings("targets"),
+            rustc_args: strings("rustc-args").unwrap_or_default(),
+            rustdoc_args: strings("rustdoc-args").unwrap_or_default(),
+            cargo_args: strings("cargo-args").unwrap_or_default(),
+        })
+    }
+
+    /// Every feature that ends up enabled for the docs.rs build of this crate
+    ///
+    /// Features that the crate doesn't have are ignored, like they are on docs.rs
+    pub fn covered_features(&self, features: &Features) -> BTreeSet<String> {
+        if self.all_features {
+            return features.features.keys().cloned().collect();
+        }
+
+        let default = (!self.no_default_features && features.features.contains_key("default"))
+            .then_some("default");
+
+        let enabled = self
+            .features
+            .iter()
+            .map(String::as_str)
+            .filter(|entry| Resolver::new(features).resolve([entry]).is_ok())
+            .chain(default);
+
+        Resolver::new(features)
+            .resolve(enabled)
+            .map(|resolved| resolved.features)
+            .unwrap_or_default()
+    }
 }
 
 /// A crate dependency
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Dependency {
     /// The name of the dependency
     pub name: String,
@@ -106,8 +542,17 @@ pub struct Dependency {
     pub features: Vec<String>,
 }
 
+impl Dependency {
+    /// The name the crate refers to this dependency as (its rename, if it was renamed)
+    pub fn key(&self) -> &str {
+        self.rename.as_deref().unwrap_or(&self.name)
+    }
+}
+
 /// The kind of dependency
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Hash, serde::Serialize)]
+#[derive(
+    Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum Kind {
     /// A normal dependency
@@ -128,3 +573,89 @@ impl From<DependencyKind> for Kind {
         }
     }
 }
+
+#[cfg(test)]
+impl Features {
+    /// Build the features of a crate from the fields of an index entry, the name and version can be left out
+    ///
+    /// e.g. `{"features": {"std": []}, "deps": [{"name": "serde", "req": "1", "optional": true}]}`
+    pub(crate) fn from_json(json: serde_json::Value) -> Self {
+        let mut entry = serde_json::json!({ "name": "test", "vers": "1.0.0" });
+        if let (Some(entry), serde_json::Value::Object(fields)) = (entry.as_object_mut(), json) {
+            entry.extend(fields);
+        }
+        Self::from_index(&serde_json::from_value(entry).expect("valid index entry"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_feature_entry() {
+        let feature = |name: &str| FeatureEntry::Feature { name: name.into() };
+        let dependency = |name: &str| FeatureEntry::Dependency { name: name.into() };
+        let dep_feature = |dep: &str, feature: &str| FeatureEntry::DependencyFeature {
+            dep: dep.into(),
+            feature: feature.into(),
+        };
+        let weak = |dep: &str, feature: &str| FeatureEntry::WeakDependencyFeature {
+            dep: dep.into(),
+            feature: feature.into(),
+        };
+
+        for (entry, expected) in [
+            ("std", feature("std")),
+            ("serde_json", feature("serde_json")),
+            ("dep:serde", dependency("serde")),
+            ("dep:serde_json", dependency("serde_json")),
+            ("serde/std", dep_feature("serde", "std")),
+            ("serde/derive", dep_feature("serde", "derive")),
+            ("serde?/std", weak("serde", "std")),
+            ("tokio?/rt-multi-thread", weak("tokio", "rt-multi-thread")),
+        ] {
+            assert_eq!(FeatureEntry::parse(entry), expected, "{}", entry);
+        }
+    }
+
+    #[test]
+    fn docs_rs_skips_bad_fields() {
+        let config = serde_json::json!({
+            "all-features": "yes",
+            "features": ["a", 1, "b"],
+            "default-target": "x86_64-unknown-linux-gnu",
+            "targets": "x86_64-unknown-linux-gnu",
+            "rustdoc-args": ["--cfg", "docsrs"],
+        });
+
+        let docs_rs = DocsRs::from_metadata(&config).unwrap();
+        assert!(!docs_rs.all_features);
+        assert_eq!(docs_rs.features, ["a", "b"]);
+        assert_eq!(
+            docs_rs.default_target.as_deref(),
+            Some("x86_64-unknown-linux-gnu")
+        );
+        assert_eq!(docs_rs.targets, None);
+        assert_eq!(docs_rs.rustdoc_args, ["--cfg", "docsrs"]);
+
+        assert!(DocsRs::from_metadata(&serde_json::json!(true)).is_none());
+    }
+
+    #[test]
+    fn feature_entry_dependency() {
+        for (entry, expected) in [
+            ("std", None),
+            ("dep:serde", Some("serde")),
+            ("serde/std", Some("serde")),
+            ("serde?/std", Some("serde")),
+        ] {
+            assert_eq!(
+                FeatureEntry::parse(entry).dependency(),
+                expected,
+                "{}",
+                entry
+            );
+        }
+    }
+}
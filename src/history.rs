@@ -0,0 +1,252 @@
+use crate::{features::Features, index::IndexEntry, resolve::Resolver};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// When each feature of a crate was added, removed or changed its default status
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FeatureHistory {
+    /// The name of the crate
+    pub name: String,
+    /// Every version that was looked at, oldest first
+    pub versions: Vec<String>,
+    /// The changes to each feature, oldest first
+    pub features: BTreeMap<String, Vec<FeatureEvent>>,
+}
+
+/// A change to a feature in a specific version
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FeatureEvent {
+    /// The version the change happened in
+    pub version: String,
+    /// What happened
+    pub kind: EventKind,
+}
+
+/// What happened to a feature
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// The feature first appeared (or came back)
+    Added,
+    /// The feature was removed
+    Removed,
+    /// The feature started being enabled by `default`
+    AddedToDefault,
+    /// The feature stopped being enabled by `default`
+    RemovedFromDefault,
+}
+
+impl FeatureHistory {
+    /// Build the history from these index entries
+    ///
+    /// Yanked versions are skipped, as are pre-releases unless `pre_release` is set.
+    /// If `semver` is provided, only the versions matching that requirement are used.
+    /// An exact version (e.g. `1.0.100`) only matches itself, even if its yanked or a pre-release
+    pub fn from_entries(
+        name: &str,
+        entries: &[IndexEntry],
+        semver: Option<&str>,
+        pre_release: bool,
+    ) -> anyhow::Result<Self> {
+        let exact = semver.filter(|semver| crate::util::is_exact_version(semver));
+        let req = semver
+            .filter(|_| exact.is_none())
+            .map(semver::VersionReq::parse)
+            .transpose()?;
+
+        let mut entries = entries
+            .iter()
+            .filter(|entry| exact.is_some() || !entry.yanked)
+            .filter_map(|entry| Some((semver::Version::parse(&entry.vers).ok()?, entry)))
+            .filter(|(version, _)| exact.is_some() || pre_release || version.pre.is_empty())
+            .filter(|(_, entry)| exact.is_none_or(|exact| entry.vers == exact))
+            .filter(|(version, _)| req.as_ref().is_none_or(|req| req.matches(version)))
+            .collect::<Vec<_>>();
+        entries.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+        anyhow::ensure!(
+            !entries.is_empty(),
+            "no versions of '{}' were found to build a history from",
+            name
+        );
+
+        let mut history = Self {
+            name: name.to_string(),
+            versions: vec![],
+            features: BTreeMap::new(),
+        };
+
+        let (mut previous, mut previous_default) = (BTreeSet::new(), BTreeSet::new());
+        for (_, entry) in entries {
+            let features = Features::from_index(entry);
+            let current = features
+                .features
+                .keys()
+                .filter(|name| *name != "default")
+                .cloned()
+                .collect::<BTreeSet<_>>();
+            let default = default_features(&features);
+
+            let mut push = |name: &String, kind| {
+                history
+                    .features
+                    .entry(name.clone())
+                    .or_default()
+                    .push(FeatureEvent {
+                        version: entry.vers.clone(),
+                        kind,
+                    })
+            };
+
+            for name in current.difference(&previous) {
+                push(name, EventKind::Added)
+            }
+            for name in previous.difference(&current) {
+                push(name, EventKind::Removed)
+            }
+            for name in default.difference(&previous_default) {
+                push(name, EventKind::AddedToDefault)
+            }
+            for name in previous_default
+                .difference(&default)
+                .filter(|name| current.contains(*name))
+            {
+                push(name, EventKind::RemovedFromDefault)
+            }
+
+            history.versions.push(entry.vers.clone());
+            previous = current;
+            previous_default = default;
+        }
+
+        Ok(history)
+    }
+}
+
+/// Every feature that is enabled by `default`
+fn default_features(features: &Features) -> BTreeSet<String> {
+    if !features.features.contains_key("default") {
+        return BTreeSet::new();
+    }
+
+    Resolver::new(features)
+        .resolve(std::iter::once("default"))
+        .map(|resolved| resolved.features)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| name != "default")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(vers: &str, features: serde_json::Value) -> IndexEntry {
+        serde_json::from_value(serde_json::json!({
+            "name": "test",
+            "vers": vers,
+            "features": features,
+        }))
+        .unwrap()
+    }
+
+    fn entries() -> Vec<IndexEntry> {
+        vec![
+            entry("1.0.0", serde_json::json!({ "std": [] })),
+            entry(
+                "1.0.1",
+                serde_json::json!({ "default": ["std"], "std": [], "derive": [] }),
+            ),
+            entry(
+                "1.0.2",
+                serde_json::json!({ "default": ["std"], "std": [] }),
+            ),
+            entry("1.0.3", serde_json::json!({ "std": [], "derive": [] })),
+            entry("1.0.4-alpha", serde_json::json!({ "std": [], "alpha": [] })),
+            {
+                let mut entry = entry("1.0.5", serde_json::json!({ "std": [], "yanked": [] }));
+                entry.yanked = true;
+                entry
+            },
+        ]
+    }
+
+    fn events(history: &FeatureHistory, name: &str) -> Vec<(String, EventKind)> {
+        history.features[name]
+            .iter()
+            .map(|event| (event.version.clone(), event.kind))
+            .collect()
+    }
+
+    fn list(list: &[(&str, EventKind)]) -> Vec<(String, EventKind)> {
+        list.iter()
+            .map(|(version, kind)| (version.to_string(), *kind))
+            .collect()
+    }
+
+    #[test]
+    fn added_removed_and_re_added() {
+        let history = FeatureHistory::from_entries("test", &entries(), None, false).unwrap();
+        assert_eq!(history.versions, ["1.0.0", "1.0.1", "1.0.2", "1.0.3"]);
+        assert_eq!(
+            history.features.keys().collect::<Vec<_>>(),
+            ["derive", "std"]
+        );
+        assert_eq!(
+            events(&history, "derive"),
+            list(&[
+                ("1.0.1", EventKind::Added),
+                ("1.0.2", EventKind::Removed),
+                ("1.0.3", EventKind::Added),
+            ])
+        );
+    }
+
+    #[test]
+    fn default_transitions() {
+        let history = FeatureHistory::from_entries("test", &entries(), None, false).unwrap();
+        assert_eq!(
+            events(&history, "std"),
+            list(&[
+                ("1.0.0", EventKind::Added),
+                ("1.0.1", EventKind::AddedToDefault),
+                ("1.0.3", EventKind::RemovedFromDefault),
+            ])
+        );
+    }
+
+    #[test]
+    fn pre_release() {
+        let history = FeatureHistory::from_entries("test", &entries(), None, true).unwrap();
+        assert_eq!(history.versions.last().unwrap(), "1.0.4-alpha");
+        assert_eq!(
+            events(&history, "alpha"),
+            list(&[("1.0.4-alpha", EventKind::Added)])
+        );
+        assert_eq!(
+            events(&history, "derive").last().unwrap(),
+            &("1.0.4-alpha".to_string(), EventKind::Removed)
+        );
+    }
+
+    #[test]
+    fn semver() {
+        let history =
+            FeatureHistory::from_entries("test", &entries(), Some("1.0.1"), false).unwrap();
+        assert_eq!(history.versions, ["1.0.1"]);
+
+        let history =
+            FeatureHistory::from_entries("test", &entries(), Some("^1.0.1"), false).unwrap();
+        assert_eq!(history.versions, ["1.0.1", "1.0.2", "1.0.3"]);
+
+        // an exact version is used even if its yanked or a pre-release
+        let history =
+            FeatureHistory::from_entries("test", &entries(), Some("1.0.5"), false).unwrap();
+        assert_eq!(history.versions, ["1.0.5"]);
+        let history =
+            FeatureHistory::from_entries("test", &entries(), Some("1.0.4-alpha"), false).unwrap();
+        assert_eq!(history.versions, ["1.0.4-alpha"]);
+
+        assert!(FeatureHistory::from_entries("test", &entries(), Some("2"), false).is_err());
+    }
+}
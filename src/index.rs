@@ -0,0 +1,157 @@
+use crate::features::Kind;
+use std::collections::HashMap;
+
+/// The sparse index used by crates.io
+pub const CRATES_IO_INDEX: &str = "https://index.crates.io";
+
+/// The `config.json` found at the root of a sparse index
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct IndexConfig {
+    /// The download template for `.crate` files
+    pub dl: String,
+    /// The base url of the web API, if the registry has one
+    pub api: Option<String>,
+    /// Whether every request to the registry must be authenticated
+    #[serde(default, rename = "auth-required")]
+    pub auth_required: bool,
+}
+
+impl IndexConfig {
+    /// Produce the download url for this crate version
+    ///
+    /// This expands the `{crate}`, `{version}`, `{prefix}`, `{lowerprefix}` and `{sha256-checksum}` markers,
+    /// or appends `/{crate}/{version}/download` if the template has none of them
+    pub fn download_url(&self, entry: &IndexEntry) -> String {
+        const MARKERS: [&str; 5] = [
+            "{crate}",
+            "{version}",
+            "{prefix}",
+            "{lowerprefix}",
+            "{sha256-checksum}",
+        ];
+
+        if !MARKERS.iter().any(|marker| self.dl.contains(marker)) {
+            return format!(
+                "{}/{}/{}/download",
+                self.dl.trim_end_matches('/'),
+                entry.name,
+                entry.vers
+            );
+        }
+
+        let prefix = crate_prefix(&entry.name);
+        self.dl
+            .replace("{crate}", &entry.name)
+            .replace("{version}", &entry.vers)
+            .replace("{lowerprefix}", &prefix.to_lowercase())
+            .replace("{prefix}", &prefix)
+            .replace("{sha256-checksum}", &entry.cksum)
+    }
+}
+
+/// A single line of a crate's index file -- one published version
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct IndexEntry {
+    /// The name of the crate
+    pub name: String,
+    /// The semantic version of the crate
+    pub vers: String,
+    /// The dependencies of this version
+    #[serde(default)]
+    pub deps: Vec<IndexDependency>,
+    /// The sha256 checksum of the `.crate` file
+    #[serde(default)]
+    pub cksum: String,
+    /// Feature map
+    #[serde(default)]
+    pub features: HashMap<String, Vec<String>>,
+    /// Feature map using the newer syntax (`dep:` and `?/`), only present in schema v2
+    #[serde(default)]
+    pub features2: HashMap<String, Vec<String>>,
+    /// Whether this version was yanked
+    #[serde(default)]
+    pub yanked: bool,
+    /// The native library this crate links to
+    pub links: Option<String>,
+    /// The minimum supported Rust version, older entries do not have this
+    #[serde(default)]
+    pub rust_version: Option<String>,
+    /// When this version was published, older entries do not have this
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub pubtime: Option<time::OffsetDateTime>,
+}
+
+impl IndexEntry {
+    /// All of the features for this entry (`features` merged with `features2`)
+    pub fn all_features(&self) -> HashMap<String, Vec<String>> {
+        self.features
+            .iter()
+            .chain(self.features2.iter())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+}
+
+/// A dependency listed in an index entry
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct IndexDependency {
+    /// The name of the dependency (this is the renamed name, if it was renamed)
+    pub name: String,
+    /// The required version of the dependency
+    pub req: String,
+    /// Features enabled for this dependency
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Whether this dependency is optional
+    #[serde(default)]
+    pub optional: bool,
+    /// Whether the default features are enabled
+    #[serde(default = "default_true")]
+    pub default_features: bool,
+    /// Which target, if any, that this dependency is required for
+    pub target: Option<String>,
+    /// The kind of the dependency (`normal`, `dev` or `build`)
+    pub kind: Option<String>,
+    /// The registry this dependency comes from, if its not the same registry
+    pub registry: Option<String>,
+    /// The actual name of the crate, if the dependency was renamed
+    pub package: Option<String>,
+}
+
+impl IndexDependency {
+    /// The kind of this dependency
+    pub fn kind(&self) -> Kind {
+        match self.kind.as_deref() {
+            Some("dev") => Kind::Development,
+            Some("build") => Kind::Build,
+            _ => Kind::Normal,
+        }
+    }
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+/// Parse the newline-delimited json of a crate's index file
+pub fn parse_entries(data: &str) -> anyhow::Result<Vec<IndexEntry>> {
+    data.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_str(line).map_err(Into::into))
+        .collect()
+}
+
+/// The path to the crate's index file, relative to the root of the index
+pub fn entry_path(crate_name: &str) -> String {
+    crate::util::chunk_name(&crate_name.to_lowercase())
+}
+
+/// The `{prefix}` for the crate, this is its index path without the trailing name
+fn crate_prefix(crate_name: &str) -> String {
+    let path = crate::util::chunk_name(crate_name);
+    match path.rsplit_once('/') {
+        Some((prefix, _)) => prefix.to_string(),
+        None => path,
+    }
+}
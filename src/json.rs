@@ -0,0 +1,361 @@
+//! The JSON output
+//!
+//! Every document has a `schema_version` and a `kind`. The version is bumped whenever a document changes
+//! in a way that isn't backwards compatible. `cargo whatfeatures --json-schema` prints the [JSON_SCHEMA]
+use crate::{
+    features::{DocsRs, FeatureEntry, Features, Kind, Workspace},
+    ActivatedFeatures, FeatureDiff, FeatureHistory, Resolved, SourceScan, Version, Why,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The version of the JSON documents
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The JSON Schema (draft 2020-12) that every document is valid for
+pub const JSON_SCHEMA: &str = include_str!("schema.json");
+
+/// A versioned JSON document
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Document<T> {
+    /// The version of the schema that this document uses
+    pub schema_version: u32,
+    /// What kind of document this is
+    pub kind: DocumentKind,
+    /// The contents of the document
+    #[serde(flatten)]
+    pub data: T,
+}
+
+impl<T> Document<T> {
+    fn new(kind: DocumentKind, data: T) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            kind,
+            data,
+        }
+    }
+}
+
+impl<T: serde::Serialize> std::fmt::Display for Document<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        f.write_str(&json)
+    }
+}
+
+/// What kind of document this is
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    /// A list of crates, from `--list`, `--short` or the features and dependencies
+    Crates,
+    /// The features enabled by `--enable`
+    Resolved,
+    /// The features that enable something, from `--why`
+    Why,
+    /// The changes between two versions, from `--diff`
+    Diff,
+    /// When features changed, from `--history`
+    History,
+    /// The features of a dependency enabled in a workspace's build, from `--resolved`
+    Activated,
+    /// Where each feature is used in the source, from `--scan`
+    Scan,
+}
+
+/// A list of crates
+///
+/// This is the same shape for `--list`, `--short` and the full output.
+/// Anything that wasn't looked up is `null`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Crates {
+    /// The crate, or workspace, that was looked up
+    pub name: String,
+    /// Every crate (or version of the crate) that was found
+    pub crates: Vec<Crate>,
+}
+
+/// A crate in a list of crates
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Crate {
+    /// The name of the crate
+    pub name: String,
+    /// The version of the crate
+    pub version: String,
+    /// Whether the crate can be published, this is always true for a crate from a registry
+    pub published: bool,
+    /// Whether this version was yanked
+    pub yanked: Option<bool>,
+    /// The license of the crate
+    pub license: Option<String>,
+    /// The minimum supported Rust version
+    pub rust_version: Option<String>,
+    /// The Rust edition
+    pub edition: Option<String>,
+    /// The native library the crate links to
+    pub links: Option<String>,
+    /// The description of the crate
+    pub description: Option<String>,
+    /// The URL of the source repository
+    pub repository: Option<String>,
+    /// The URL of the homepage
+    pub homepage: Option<String>,
+    /// The crates.io categories
+    pub categories: Option<Vec<String>>,
+    /// When this version was published
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_at: Option<time::OffsetDateTime>,
+    /// Every feature, and what it enables
+    pub features: Option<BTreeMap<String, Vec<FeatureEntry>>>,
+    /// The features that cargo implicitly created for optional dependencies
+    pub implicit_features: Option<BTreeSet<String>>,
+    /// The documentation for each feature, from the comments in its `Cargo.toml`
+    pub feature_docs: Option<BTreeMap<String, String>>,
+    /// How docs.rs builds the crate
+    pub docs_rs: Option<DocsRs>,
+    /// The features that aren't enabled when docs.rs builds the crate
+    pub undocumented_features: Option<BTreeSet<String>>,
+    /// Every dependency of the crate
+    pub dependencies: Option<Vec<CrateDependency>>,
+}
+
+impl Crate {
+    fn new(name: &str, version: &str, published: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            published,
+            yanked: None,
+            license: None,
+            rust_version: None,
+            edition: None,
+            links: None,
+            description: None,
+            repository: None,
+            homepage: None,
+            categories: None,
+            created_at: None,
+            features: None,
+            implicit_features: None,
+            feature_docs: None,
+            docs_rs: None,
+            undocumented_features: None,
+            dependencies: None,
+        }
+    }
+}
+
+/// A dependency of a crate
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CrateDependency {
+    /// The name of the dependency
+    pub name: String,
+    /// What this dependency was renamed to, if it was renamed
+    pub rename: Option<String>,
+    /// The required version of the dependency
+    pub req: String,
+    /// The kind of dependency
+    pub kind: Kind,
+    /// Which target, if any, that this dependency is required for
+    pub target: Option<String>,
+    /// Whether the dependency is optional
+    pub optional: bool,
+    /// Whether cargo created a feature for this optional dependency
+    pub implicit_feature: bool,
+    /// The features enabled on the dependency
+    pub features: Vec<String>,
+}
+
+/// The features enabled by a set of features
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ResolvedFeatures {
+    /// The name of the crate
+    pub name: String,
+    /// The version of the crate
+    pub version: String,
+    /// Every feature that ends up enabled
+    pub features: BTreeSet<String>,
+    /// The optional dependencies that are activated
+    pub optional_dependencies: BTreeSet<String>,
+    /// The features enabled on each dependency
+    pub dependency_features: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// The features that enable a feature or an optional dependency
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WhyEnabled {
+    /// The name of the crate
+    pub name: String,
+    /// The version of the crate
+    pub version: String,
+    /// The feature or optional dependency
+    pub target: String,
+    /// Every feature that directly enables the target
+    pub enabled_by: Vec<Why>,
+    /// Every path from a feature that nothing else enables, down to the target
+    pub paths: Vec<Vec<String>>,
+}
+
+/// The features of a dependency enabled in a workspace's build
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Activated {
+    /// Every version of the dependency in the build
+    pub dependencies: Vec<ActivatedFeatures>,
+}
+
+/// Every version of a crate, from `--list`
+pub fn create_crates_from_versions(
+    name: &str,
+    versions: impl IntoIterator<Item = Version>,
+) -> Document<Crates> {
+    let crates = versions
+        .into_iter()
+        .map(|version| Crate {
+            yanked: Some(version.yanked),
+            license: version.license.clone(),
+            rust_version: version.rust_version.clone(),
+            created_at: version.created_at,
+            ..Crate::new(&version.name, &version.version, true)
+        })
+        .collect();
+
+    Document::new(
+        DocumentKind::Crates,
+        Crates {
+            name: name.to_string(),
+            crates,
+        },
+    )
+}
+
+/// The name and version of every crate in a workspace, from `--short`
+pub fn create_crates_from_workspace<'a>(
+    workspace: &str,
+    crates: impl IntoIterator<Item = (&'a String, &'a String, bool)>,
+) -> Document<Crates> {
+    let crates = crates
+        .into_iter()
+        .map(|(name, version, published)| Crate::new(name, version, published))
+        .collect();
+
+    Document::new(
+        DocumentKind::Crates,
+        Crates {
+            name: workspace.to_string(),
+            crates,
+        },
+    )
+}
+
+/// The features and dependencies of every crate in a workspace
+pub fn workspace(workspace: Workspace) -> Document<Crates> {
+    let mut crates = workspace
+        .map
+        .into_values()
+        .map(|features| Crate {
+            dependencies: Some(dependencies(&features)),
+            undocumented_features: features.undocumented_features(),
+            features: Some(features.features.into_iter().collect()),
+            implicit_features: Some(features.implicit_features),
+            docs_rs: features.docs_rs,
+            license: features.license,
+            rust_version: features.rust_version,
+            edition: features.edition,
+            links: features.links,
+            description: features.description,
+            repository: features.repository,
+            homepage: features.homepage,
+            categories: Some(features.categories),
+            feature_docs: Some(features.docs.into_iter().collect()),
+            ..Crate::new(&features.name, &features.version, features.published)
+        })
+        .collect::<Vec<_>>();
+    crates.sort_by(|left, right| left.name.cmp(&right.name));
+
+    Document::new(
+        DocumentKind::Crates,
+        Crates {
+            name: workspace.hint,
+            crates,
+        },
+    )
+}
+
+fn dependencies(features: &Features) -> Vec<CrateDependency> {
+    let optional = features.optional_deps.iter().map(|deps| (deps, true));
+    let required = features.required_deps.iter().map(|deps| (deps, false));
+
+    let mut deps = optional
+        .chain(required)
+        .flat_map(|((kind, deps), optional)| {
+            deps.iter().map(move |dep| CrateDependency {
+                name: dep.name.clone(),
+                rename: dep.rename.clone(),
+                req: dep.req.clone(),
+                kind: *kind,
+                target: dep.target.clone(),
+                optional,
+                implicit_feature: optional && !features.hidden_deps.contains(dep.key()),
+                features: dep.features.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    deps.sort_by(|left, right| {
+        (left.kind, &left.name, &left.target).cmp(&(right.kind, &right.name, &right.target))
+    });
+    deps
+}
+
+/// The features enabled by `--enable`
+pub fn resolved(features: &Features, resolved: &Resolved) -> Document<ResolvedFeatures> {
+    Document::new(
+        DocumentKind::Resolved,
+        ResolvedFeatures {
+            name: features.name.clone(),
+            version: features.version.clone(),
+            features: resolved.features.clone(),
+            optional_dependencies: resolved.optional_deps.clone(),
+            dependency_features: resolved.dep_features.clone(),
+        },
+    )
+}
+
+/// The features that enable something, from `--why`
+pub fn why(features: &Features, why: &Why) -> Document<WhyEnabled> {
+    Document::new(
+        DocumentKind::Why,
+        WhyEnabled {
+            name: features.name.clone(),
+            version: features.version.clone(),
+            target: why.name.clone(),
+            enabled_by: why.enabled_by.clone(),
+            paths: why.paths(),
+        },
+    )
+}
+
+/// The changes between two versions, from `--diff`
+pub fn diff(diff: &FeatureDiff) -> Document<FeatureDiff> {
+    Document::new(DocumentKind::Diff, diff.clone())
+}
+
+/// When features changed, from `--history`
+pub fn history(history: &FeatureHistory) -> Document<FeatureHistory> {
+    Document::new(DocumentKind::History, history.clone())
+}
+
+/// Where each feature is used in the source, from `--scan`
+pub fn scan(scan: &SourceScan) -> Document<SourceScan> {
+    Document::new(DocumentKind::Scan, scan.clone())
+}
+
+/// The features of a dependency enabled in a workspace's build, from `--resolved`
+pub fn activated(activated: &[ActivatedFeatures]) -> Document<Activated> {
+    Document::new(
+        DocumentKind::Activated,
+        Activated {
+            dependencies: activated.to_vec(),
+        },
+    )
+}
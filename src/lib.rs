@@ -3,23 +3,72 @@
 print out features and dependencies for a specific crate
 */
 
+mod activation;
+mod apply;
 mod args;
+mod cache;
+mod cargo_config;
 mod client;
+mod credentials;
+mod dependency_line;
+mod diff;
 mod features;
+mod history;
+mod index;
+pub mod json;
+mod local_index;
 mod printer;
 mod registry;
+mod resolve;
+mod scan;
+mod target;
 mod util;
 
+#[doc(inline)]
+pub use activation::{ActivatedFeatures, Requester};
+
+#[doc(inline)]
+pub use apply::{DependencyEntry, DependencySource, Manifest};
+
+#[doc(inline)]
+pub use cache::{Cache, CacheEntry, CacheLimits, Collected};
+
+#[doc(inline)]
+pub use cargo_config::{RegistryConfig, CRATES_IO};
+
 #[doc(inline)]
 pub use client::{Client, Version};
 
-// TODO remove this
-#[doc(hidden)]
-pub use client::json;
+#[doc(inline)]
+pub use dependency_line::DependencyLine;
+
+#[doc(inline)]
+pub use diff::{DependencyChange, DependencyDiff, EntryDiff, FeatureDiff, Renamed};
+
+#[doc(inline)]
+pub use features::{Dependency, DocsRs, FeatureEntry, Features, Kind, Workspace};
+
+#[doc(inline)]
+pub use history::{EventKind, FeatureEvent, FeatureHistory};
+
+#[doc(inline)]
+pub use index::{IndexConfig, IndexDependency, IndexEntry, CRATES_IO_INDEX};
+
+#[doc(inline)]
+pub use local_index::LocalIndex;
 
 #[doc(inline)]
 pub use registry::{Crate, Registry, YankState};
 
+#[doc(inline)]
+pub use resolve::{Resolved, Resolver, Why};
+
+#[doc(inline)]
+pub use scan::{FeatureUsage, SourceScan, UsageKind};
+
+#[doc(inline)]
+pub use target::Target;
+
 #[doc(inline)]
 pub use args::{Args, PkgId};
 
@@ -40,20 +89,33 @@ pub enum OfflineError {
 }
 
 impl OfflineError {
-    /// Converts this type to an Error type
+    /// Converts this type to an Error type, for crates.io
     pub fn to_error(&self) -> anyhow::Error {
-        let err = match self {
-            Self::List => {
-                "must be able to connect to https://crates.io to list versions"
-            }
-            Self::Latest =>{
-                "cannot find that crate cached, you must be able to connect to https://crates.io to get the latest version"
-            } ,
-            Self::CacheMiss => {
-                "crate not found in local registry or cache. must be able to connect to https://crates.io to fetch it"
-            },
+        self.to_registry_error(&RegistryConfig::crates_io())
+    }
+
+    /// Converts this type to an Error type, naming the registry that couldn't be reached
+    pub fn to_registry_error(&self, registry: &RegistryConfig) -> anyhow::Error {
+        let remote = if *registry == RegistryConfig::crates_io() {
+            "https://crates.io".to_string()
+        } else {
+            format!("the '{}' registry ({})", registry.name, registry.index)
         };
-        anyhow::anyhow!(err)
+
+        match self {
+            Self::List => anyhow::anyhow!(
+                "crate not found in cargo's index cache. must be able to connect to {} to list versions",
+                remote
+            ),
+            Self::Latest => anyhow::anyhow!(
+                "cannot find that crate cached, you must be able to connect to {} to get the latest version",
+                remote
+            ),
+            Self::CacheMiss => anyhow::anyhow!(
+                "crate not found in local registry or cache. must be able to connect to {} to fetch it",
+                remote
+            ),
+        }
     }
 }
 
@@ -62,31 +124,40 @@ impl OfflineError {
 pub enum Lookup {
     /// A partial lookup -- this has to cache the crate
     Partial(Version),
-    /// The latest version from the cache
-    LocalCache(features::Workspace),
+    /// A version from the local cache
+    LocalCache {
+        /// The features for the crate
+        workspace: features::Workspace,
+        /// Whether it is known to have been yanked
+        yanked: YankState,
+        /// Whether this is just the newest version that was cached, rather than the one requested
+        possibly_old: bool,
+    },
     /// A local workspace
     Workspace(features::Workspace),
 }
 
-/// Find this 'pkgid'
-pub fn lookup(pkg_id: &PkgId, client: &Option<Client>, is_local: bool) -> anyhow::Result<Lookup> {
+/// Find this 'pkgid' in the provided registry
+///
+/// Pre-releases are only considered for the latest version if `pre_release` is set (or if there is nothing else)
+pub fn lookup(
+    pkg_id: &PkgId,
+    client: &Option<Client>,
+    registry: &RegistryConfig,
+    is_local: bool,
+    pre_release: bool,
+) -> anyhow::Result<Lookup> {
     match pkg_id {
         // lookup the latest version
-        PkgId::Remote { name, semver } => {
+        PkgId::Remote { name, semver, .. } => {
             let client = match &client {
                 Some(client) => client,
-                None => {
-                    return Registry::from_local()?
-                        .maybe_latest(&name)
-                        .ok_or_else(|| OfflineError::Latest.to_error())?
-                        .get_features()
-                        .map(Lookup::LocalCache)
-                }
+                None => return lookup_offline(name, semver.as_deref(), registry, pre_release),
             };
 
             let pkg = match semver {
                 Some(semver) => client.get_version(name, semver),
-                None => client.get_latest(name),
+                None => client.get_latest(name, pre_release),
             }
             .map_err(|_err| cannot_find(pkg_id))?;
 
@@ -99,9 +170,115 @@ pub fn lookup(pkg_id: &PkgId, client: &Option<Client>, is_local: bool) -> anyhow
     }
 }
 
+/// Find the crate using cargo's index cache, and the unpacked crates from the local registry
+fn lookup_offline(
+    name: &str,
+    semver: Option<&str>,
+    registry: &RegistryConfig,
+    pre_release: bool,
+) -> anyhow::Result<Lookup> {
+    let local = Registry::for_registry(registry).ok();
+    let entries = LocalIndex::for_registry(registry)
+        .and_then(|index| index.list_entries(name))
+        .unwrap_or_default();
+
+    // an exact version is the one that was asked for, anything else might have a newer match
+    let possibly_old = !semver.is_some_and(util::is_exact_version);
+
+    let entry = match semver {
+        Some(semver) => util::find_matching(
+            entries
+                .iter()
+                .filter(|entry| util::is_exact_version(semver) || !entry.yanked),
+            |entry| &entry.vers,
+            semver,
+        ),
+        None => util::find_latest(
+            entries.iter().filter(|entry| !entry.yanked),
+            |entry| &entry.vers,
+            pre_release,
+        ),
+    };
+
+    if let Some(entry) = entry {
+        let crate_ = match &local {
+            Some(local) => local.get(name, &entry.vers)?,
+            None => None,
+        };
+
+        let workspace = match crate_ {
+            Some(crate_) => crate_.get_features()?,
+            None => features::Workspace::from_index(entry, &registry.index),
+        };
+
+        return Ok(Lookup::LocalCache {
+            workspace,
+            yanked: entry.yanked.into(),
+            possibly_old,
+        });
+    }
+
+    let (crate_, err) = match (&local, semver) {
+        (Some(local), Some(semver)) => {
+            (local.maybe_matching(name, semver)?, OfflineError::CacheMiss)
+        }
+        (Some(local), None) => (local.maybe_latest(name, pre_release)?, OfflineError::Latest),
+        (None, Some(..)) => (None, OfflineError::CacheMiss),
+        (None, None) => (None, OfflineError::Latest),
+    };
+
+    crate_
+        .ok_or_else(|| err.to_registry_error(registry))?
+        .get_features()
+        .map(|workspace| Lookup::LocalCache {
+            workspace,
+            yanked: YankState::UnknownLocal,
+            possibly_old,
+        })
+}
+
+/// Get the crate for this version from the local registry, or download it into our cache
+pub fn fetch_crate(
+    version: &Version,
+    client: &Option<Client>,
+    registry: &RegistryConfig,
+) -> anyhow::Result<Crate> {
+    let Version { name, version, .. } = version;
+    if let Some(crate_) = Registry::for_registry(registry)?.get(name, version)? {
+        return Ok(crate_);
+    }
+
+    client
+        .as_ref()
+        .ok_or_else(|| OfflineError::CacheMiss.to_registry_error(registry))?
+        .cache_crate(name, version)
+}
+
 fn cannot_find(pkg_id: &PkgId) -> anyhow::Error {
     anyhow::anyhow!(
         "cannot find a crate matching '{}'. maybe it was yanked?",
         pkg_id
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_error_names_the_registry() {
+        assert_eq!(
+            OfflineError::List.to_error().to_string(),
+            "crate not found in cargo's index cache. must be able to connect to https://crates.io to list versions"
+        );
+
+        let registry = RegistryConfig {
+            name: "my-registry".into(),
+            index: "https://index.example.com".into(),
+        };
+        assert_eq!(
+            OfflineError::CacheMiss.to_registry_error(&registry).to_string(),
+            "crate not found in local registry or cache. must be able to connect to the 'my-registry' registry (https://index.example.com) to fetch it"
+        );
+    }
+}
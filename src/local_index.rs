@@ -0,0 +1,210 @@
+use crate::{
+    cargo_config::RegistryConfig,
+    client::Version,
+    features::Workspace,
+    index::{self, IndexEntry},
+};
+use anyhow::Context as _;
+use std::path::PathBuf;
+
+/// The version of cargo's index cache files that we understand
+const CACHE_VERSION: u8 = 3;
+
+/// Cargo's on-disk cache of a registry index (`$CARGO_HOME/registry/index/*/.cache`)
+///
+/// This contains every crate that cargo has ever resolved, even if it never downloaded it
+pub struct LocalIndex {
+    registry: RegistryConfig,
+    dirs: Vec<PathBuf>,
+}
+
+impl LocalIndex {
+    /// Find cargo's index caches for this registry
+    pub fn for_registry(registry: &RegistryConfig) -> anyhow::Result<Self> {
+        let dirs = home::cargo_home()?
+            .join("registry")
+            .join("index")
+            .read_dir()
+            .with_context(|| "expected to have a local registry index")?
+            .flatten()
+            .filter(|dir| {
+                dir.file_name()
+                    .to_str()
+                    .filter(|name| registry.owns_cargo_dir(name))
+                    .is_some()
+            })
+            .map(|dir| dir.path().join(".cache"))
+            .filter(|dir| dir.is_dir())
+            .collect();
+
+        Ok(Self {
+            registry: registry.clone(),
+            dirs,
+        })
+    }
+
+    /// Get all of the cached index entries for this crate
+    pub fn list_entries(&self, crate_name: &str) -> anyhow::Result<Vec<IndexEntry>> {
+        let path = index::entry_path(crate_name);
+
+        let mut entries = Vec::<IndexEntry>::new();
+        for file in self.dirs.iter().map(|dir| dir.join(&path)) {
+            if !file.is_file() {
+                continue;
+            }
+
+            let data = std::fs::read(&file)
+                .with_context(|| anyhow::anyhow!("cannot read {}", file.display()))?;
+            for entry in parse_cache_file(&data)
+                .with_context(|| anyhow::anyhow!("cannot parse {}", file.display()))?
+            {
+                if !entries.iter().any(|e| e.vers == entry.vers) {
+                    entries.push(entry)
+                }
+            }
+        }
+
+        anyhow::ensure!(
+            !entries.is_empty(),
+            "{} was not found in cargo's index cache",
+            crate_name
+        );
+        Ok(entries)
+    }
+
+    /// Get all of the cached versions for this crate, newest first
+    pub fn list_versions(&self, crate_name: &str) -> anyhow::Result<Vec<Version>> {
+        let mut versions = self
+            .list_entries(crate_name)?
+            .iter()
+            .map(Version::from_index)
+            .collect::<Vec<_>>();
+        versions
+            .sort_by(|left, right| crate::util::compare_versions(&right.version, &left.version));
+        Ok(versions)
+    }
+
+    /// Get the features and dependencies for this crate version, from the cached index entry
+    pub fn get_features(&self, crate_name: &str, crate_version: &str) -> anyhow::Result<Workspace> {
+        self.list_entries(crate_name)?
+            .iter()
+            .find(|entry| entry.vers == crate_version)
+            .map(|entry| Workspace::from_index(entry, &self.registry.index))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{} = {} was not found in cargo's index cache",
+                    crate_name,
+                    crate_version
+                )
+            })
+    }
+}
+
+/// Parse one of cargo's index cache files
+///
+/// The format is a version byte, a `u32` index format version, the cache key (e.g. an etag) terminated by a `0`
+/// and then pairs of `<version>\0<json>\0` for each published version
+pub fn parse_cache_file(data: &[u8]) -> anyhow::Result<Vec<IndexEntry>> {
+    let (&version, rest) = data
+        .split_first()
+        .with_context(|| "empty index cache file")?;
+    anyhow::ensure!(
+        version == CACHE_VERSION,
+        "unsupported index cache version: {}",
+        version
+    );
+
+    let rest = rest
+        .get(4..)
+        .with_context(|| "truncated index cache file")?;
+
+    let mut parts = rest.split(|&c| c == 0).skip(1);
+    let mut entries = vec![];
+    while let (Some(_version), Some(json)) = (parts.next(), parts.next()) {
+        // like cargo, skip the entries we don't understand (e.g. ones written by a newer cargo)
+        if let Ok(entry) = serde_json::from_slice(json) {
+            entries.push(entry)
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_file(version: u8, pairs: &[(&str, &str)]) -> Vec<u8> {
+        let mut data = vec![version];
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(b"\"etag\"\0");
+        for (version, json) in pairs {
+            data.extend_from_slice(version.as_bytes());
+            data.push(0);
+            data.extend_from_slice(json.as_bytes());
+            data.push(0);
+        }
+        data
+    }
+
+    fn versions(entries: &[IndexEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.vers.as_str()).collect()
+    }
+
+    #[test]
+    fn parse() {
+        let data = cache_file(
+            CACHE_VERSION,
+            &[
+                ("1.0.0", r#"{"name":"foo","vers":"1.0.0","yanked":true}"#),
+                (
+                    "1.1.0",
+                    r#"{"name":"foo","vers":"1.1.0","features":{"a":[]}}"#,
+                ),
+            ],
+        );
+        let entries = parse_cache_file(&data).unwrap();
+        assert_eq!(versions(&entries), ["1.0.0", "1.1.0"]);
+        assert!(entries[0].yanked);
+        assert!(entries[1].features.contains_key("a"));
+    }
+
+    #[test]
+    fn header() {
+        assert!(parse_cache_file(&[]).is_err());
+        assert!(parse_cache_file(&[CACHE_VERSION, 2, 0]).is_err());
+        assert!(parse_cache_file(&cache_file(CACHE_VERSION + 1, &[])).is_err());
+        assert!(parse_cache_file(&cache_file(CACHE_VERSION, &[]))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn skips_bad_entries() {
+        let data = cache_file(
+            CACHE_VERSION,
+            &[
+                ("0.1.0", r#"{"name":"foo","vers":"0.1.0","yanked":"maybe"}"#),
+                ("0.2.0", ""),
+                ("0.3.0", r#"{"name":"foo","vers":"0.3.0"}"#),
+            ],
+        );
+        assert_eq!(versions(&parse_cache_file(&data).unwrap()), ["0.3.0"]);
+    }
+
+    #[test]
+    fn truncated_pair() {
+        let mut data = cache_file(
+            CACHE_VERSION,
+            &[("1.0.0", r#"{"name":"foo","vers":"1.0.0"}"#)],
+        );
+        data.extend_from_slice(b"1.1.0\0{\"name\":\"foo\",\"ve");
+        assert_eq!(versions(&parse_cache_file(&data).unwrap()), ["1.0.0"]);
+
+        let mut data = cache_file(
+            CACHE_VERSION,
+            &[("1.0.0", r#"{"name":"foo","vers":"1.0.0"}"#)],
+        );
+        data.extend_from_slice(b"1.1.0");
+        assert_eq!(versions(&parse_cache_file(&data).unwrap()), ["1.0.0"]);
+    }
+}
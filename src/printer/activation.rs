@@ -0,0 +1,99 @@
+use super::{
+    labels,
+    tree::{Node, Printer},
+};
+use crate::{
+    activation::{ActivatedFeatures, Requester},
+    Theme,
+};
+use std::io::Write;
+
+pub struct ActivationPrinter<'a, W: ?Sized> {
+    writer: &'a mut W,
+    theme: Theme,
+    activated: &'a [ActivatedFeatures],
+}
+
+impl<'a, W: ?Sized> ActivationPrinter<'a, W>
+where
+    W: Write,
+{
+    pub fn new(writer: &'a mut W, activated: &'a [ActivatedFeatures], theme: Theme) -> Self {
+        Self {
+            writer,
+            theme,
+            activated,
+        }
+    }
+
+    pub fn print(self) -> std::io::Result<()> {
+        let Self {
+            writer,
+            theme,
+            activated,
+        } = self;
+
+        for activated in activated {
+            make_activated_node(activated, &theme).print(writer, &theme)?;
+        }
+        Ok(())
+    }
+}
+
+fn make_activated_node(activated: &ActivatedFeatures, theme: &Theme) -> Node {
+    let header = format!(
+        "{} = \"{}\"",
+        theme.name.paint(&activated.name),
+        theme.version.paint(&activated.version),
+    );
+
+    let enabled = if activated.enabled.is_empty() {
+        Node::empty(theme.no_features.paint(labels::NO_ENABLED_FEATURES))
+    } else {
+        let iter = activated.enabled.iter().map(|(name, requesters)| {
+            let name = if name == "default" {
+                theme.default.paint(name)
+            } else {
+                theme.feature_name.paint(name)
+            };
+            Node::new(
+                name,
+                requesters.iter().map(|req| format_requester(req, theme)),
+            )
+        });
+        Node::new(theme.features.paint(labels::ENABLED_FEATURES), iter)
+    };
+
+    let disabled = if activated.disabled.is_empty() {
+        Node::empty(theme.no_features.paint(labels::NO_DISABLED_FEATURES))
+    } else {
+        let iter = activated.disabled.iter().map(|name| {
+            if name.starts_with('_') {
+                theme.probably_internal.paint(name)
+            } else {
+                theme.feature_implies.paint(name)
+            }
+        });
+        Node::new(theme.features.paint(labels::DISABLED_FEATURES), iter)
+    };
+
+    Node::new(header, vec![enabled, disabled])
+}
+
+fn format_requester(requester: &Requester, theme: &Theme) -> String {
+    let name = format!(
+        "{} {} = \"{}\"",
+        labels::REQUESTED_BY,
+        theme.name.paint(&requester.name),
+        theme.version.paint(&requester.version),
+    );
+
+    match &requester.via {
+        Some(via) => format!(
+            "{} (via its {} feature)",
+            name,
+            theme.feature_name.paint(via)
+        ),
+        None => name,
+    }
+}
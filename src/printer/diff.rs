@@ -0,0 +1,185 @@
+use super::{
+    labels,
+    tree::{Node, Printer},
+};
+use crate::{
+    diff::{DependencyDiff, EntryDiff, FeatureDiff},
+    features::{Dependency, Kind},
+    Theme,
+};
+use std::io::Write;
+
+pub struct DiffPrinter<'a, W: ?Sized> {
+    writer: &'a mut W,
+    theme: Theme,
+    diff: &'a FeatureDiff,
+}
+
+impl<'a, W: ?Sized> DiffPrinter<'a, W>
+where
+    W: Write,
+{
+    pub fn new(writer: &'a mut W, diff: &'a FeatureDiff, theme: Theme) -> Self {
+        Self {
+            writer,
+            theme,
+            diff,
+        }
+    }
+
+    pub fn print(self) -> std::io::Result<()> {
+        let Self {
+            writer,
+            theme,
+            diff,
+        } = self;
+
+        let header = format!(
+            "{} = \"{}\" -> \"{}\"",
+            theme.name.paint(&diff.name),
+            theme.version.paint(&diff.old_version),
+            theme.version.paint(&diff.new_version),
+        );
+
+        if diff.is_empty() {
+            let node = Node::empty(theme.no_features.paint(labels::NO_CHANGES));
+            return Node::new(header, std::iter::once(node)).print(writer, &theme);
+        }
+
+        let nodes = vec![
+            make_features_node(diff, &theme),
+            make_deps_node(diff, &theme),
+        ];
+        Node::new(header, nodes).print(writer, &theme)
+    }
+}
+
+fn make_features_node(diff: &FeatureDiff, theme: &Theme) -> Node {
+    let mut nodes = vec![];
+
+    nodes.extend(
+        diff.added_features
+            .iter()
+            .map(|name| added(theme.feature_name.paint(name), theme)),
+    );
+    nodes.extend(
+        diff.removed_features
+            .iter()
+            .map(|name| removed(theme.feature_name.paint(name), theme)),
+    );
+    nodes.extend(diff.renamed_features.iter().map(|renamed| {
+        changed(
+            format!(
+                "{} -> {} ({})",
+                theme.feature_name.paint(&renamed.from),
+                theme.feature_name.paint(&renamed.to),
+                labels::RENAMED
+            ),
+            theme,
+        )
+    }));
+
+    if !diff.default.is_empty() {
+        nodes.push(make_entries_node(
+            theme.default.paint(labels::DEFAULT),
+            &diff.default,
+            theme,
+        ));
+    }
+
+    nodes.extend(
+        diff.changed_features.iter().map(|(name, entries)| {
+            make_entries_node(theme.feature_name.paint(name), entries, theme)
+        }),
+    );
+
+    if nodes.is_empty() {
+        return Node::empty(theme.no_features.paint(labels::NO_FEATURE_CHANGES));
+    }
+    Node::new(theme.features.paint(labels::FEATURES), nodes)
+}
+
+fn make_entries_node(name: impl ToString, diff: &EntryDiff, theme: &Theme) -> Node {
+    let added = diff
+        .added
+        .iter()
+        .map(|entry| added(theme.feature_implies.paint(entry), theme));
+    let removed = diff
+        .removed
+        .iter()
+        .map(|entry| removed(theme.feature_implies.paint(entry), theme));
+    Node::new(name, added.chain(removed))
+}
+
+fn make_deps_node(diff: &FeatureDiff, theme: &Theme) -> Node {
+    if diff.dependencies.is_empty() {
+        return Node::empty(theme.no_required_deps.paint(labels::NO_DEPENDENCY_CHANGES));
+    }
+
+    let nodes = diff.dependencies.iter().map(|(kind, deps)| {
+        let name = match kind {
+            Kind::Normal => theme.normal_deps.paint(labels::NORMAL),
+            Kind::Development => theme.dev_deps.paint(labels::DEVELOPMENT),
+            Kind::Build => theme.build_deps.paint(labels::BUILD),
+        };
+        Node::new(name, make_dep_nodes(deps, theme))
+    });
+
+    Node::new(theme.required_deps.paint(labels::DEPENDENCIES), nodes)
+}
+
+fn make_dep_nodes<'a>(
+    deps: &'a DependencyDiff,
+    theme: &'a Theme,
+) -> impl Iterator<Item = Node> + 'a {
+    let added = deps
+        .added
+        .iter()
+        .map(move |dep| added(format_dep(dep, theme), theme));
+    let removed = deps
+        .removed
+        .iter()
+        .map(move |dep| removed(format_dep(dep, theme), theme));
+
+    let changed = deps.changed.iter().map(move |change| {
+        let optional = |optional| if optional { " (optional)" } else { "" };
+        let mut text = format!(
+            "{} = \"{}\"{} -> \"{}\"{}",
+            theme.name.paint(&change.name),
+            theme.version.paint(&change.old_req),
+            optional(change.old_optional),
+            theme.version.paint(&change.new_req),
+            optional(change.new_optional),
+        );
+        if let Some(target) = &change.target {
+            text = format!("{} for {}", text, theme.target.paint(target));
+        }
+        changed(text, theme)
+    });
+
+    added.chain(removed).chain(changed)
+}
+
+fn format_dep(dep: &Dependency, theme: &Theme) -> String {
+    let text = format!(
+        "{} = \"{}\"",
+        theme.name.paint(dep.key()),
+        theme.version.paint(&dep.req),
+    );
+    match &dep.target {
+        Some(target) => format!("{} for {}", text, theme.target.paint(target)),
+        None => text,
+    }
+}
+
+fn added(text: impl std::fmt::Display, theme: &Theme) -> Node {
+    Node::empty(format!("{} {}", theme.added.paint("+"), text))
+}
+
+fn removed(text: impl std::fmt::Display, theme: &Theme) -> Node {
+    Node::empty(format!("{} {}", theme.removed.paint("-"), text))
+}
+
+fn changed(text: impl std::fmt::Display, theme: &Theme) -> Node {
+    Node::empty(format!("{} {}", theme.changed.paint("~"), text))
+}
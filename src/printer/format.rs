@@ -0,0 +1,15 @@
+/// How the output should be formatted
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Format {
+    /// A human-readable tree
+    #[default]
+    Text,
+    /// JSON
+    Json,
+    /// Markdown headings, lists and tables
+    Markdown,
+    /// A Graphviz digraph of the features
+    Dot,
+    /// A Mermaid flowchart of the features
+    Mermaid,
+}
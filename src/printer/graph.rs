@@ -0,0 +1,273 @@
+use crate::{
+    features::{FeatureEntry, Features},
+    Format, Options, Resolved, Resolver,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+};
+
+/// Write the feature graph of each crate, as either a Graphviz digraph or a Mermaid flowchart
+pub(super) fn write_graph<W: Write + ?Sized>(
+    writer: &mut W,
+    list: &[&Features],
+    options: &Options,
+) -> std::io::Result<()> {
+    let graphs = list
+        .iter()
+        .filter(|features| features.published || options.show_private)
+        .map(|features| Graph::new(features))
+        .collect::<Vec<_>>();
+
+    match options.format {
+        Format::Mermaid => write_mermaid(writer, &graphs),
+        _ => write_dot(writer, &graphs),
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum NodeKind {
+    Feature,
+    Dependency,
+    DependencyFeature,
+}
+
+struct GraphNode {
+    kind: NodeKind,
+    label: String,
+    default: bool,
+}
+
+struct Edge {
+    from: usize,
+    to: usize,
+    weak: bool,
+}
+
+/// The features of a crate as a graph, with an edge from each feature to everything that it enables
+struct Graph {
+    name: String,
+    version: String,
+    nodes: Vec<GraphNode>,
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    fn new(features: &Features) -> Self {
+        let default = Resolver::new(features)
+            .resolve(features.features.get("default").map(|_| "default"))
+            .unwrap_or_default();
+
+        let optional = features
+            .optional_deps
+            .values()
+            .flatten()
+            .map(|dep| dep.key())
+            .collect::<BTreeSet<_>>();
+
+        let mut this = Self {
+            name: features.name.clone(),
+            version: features.version.clone(),
+            nodes: vec![],
+            edges: vec![],
+        };
+        let mut ids = BTreeMap::new();
+
+        let mut names = features.features.keys().collect::<Vec<_>>();
+        names.sort();
+
+        for name in names {
+            let from = this.node(&mut ids, NodeKind::Feature, name, &default);
+            for entry in &features.features[name] {
+                let (to, weak) = match entry {
+                    FeatureEntry::Feature { name } => (
+                        this.node(&mut ids, NodeKind::Feature, name, &default),
+                        false,
+                    ),
+                    FeatureEntry::Dependency { name } => (
+                        this.node(&mut ids, NodeKind::Dependency, name, &default),
+                        false,
+                    ),
+                    FeatureEntry::DependencyFeature { dep, feature }
+                    | FeatureEntry::WeakDependencyFeature { dep, feature } => {
+                        let weak = matches!(entry, FeatureEntry::WeakDependencyFeature { .. });
+                        let label = format!("{}/{}", dep, feature);
+                        let to = this.node(&mut ids, NodeKind::DependencyFeature, &label, &default);
+
+                        // a (non-weak) dependency feature also activates an optional dependency
+                        if !weak && optional.contains(dep.as_str()) {
+                            let dep = this.node(&mut ids, NodeKind::Dependency, dep, &default);
+                            this.edge(to, dep, false);
+                        }
+                        (to, weak)
+                    }
+                };
+                this.edge(from, to, weak);
+            }
+        }
+
+        this
+    }
+
+    fn node(
+        &mut self,
+        ids: &mut BTreeMap<(NodeKind, String), usize>,
+        kind: NodeKind,
+        label: &str,
+        default: &Resolved,
+    ) -> usize {
+        *ids.entry((kind, label.to_string())).or_insert_with(|| {
+            let default = match kind {
+                NodeKind::Feature => default.features.contains(label),
+                NodeKind::Dependency => default.optional_deps.contains(label),
+                NodeKind::DependencyFeature => label
+                    .split_once('/')
+                    .and_then(|(dep, feature)| {
+                        let features = default.dep_features.get(dep.trim_end_matches('?'))?;
+                        Some(features.contains(feature))
+                    })
+                    .unwrap_or(false),
+            };
+
+            self.nodes.push(GraphNode {
+                kind,
+                label: label.to_string(),
+                default,
+            });
+            self.nodes.len() - 1
+        })
+    }
+
+    fn edge(&mut self, from: usize, to: usize, weak: bool) {
+        if !self
+            .edges
+            .iter()
+            .any(|edge| edge.from == from && edge.to == to)
+        {
+            self.edges.push(Edge { from, to, weak })
+        }
+    }
+}
+
+fn write_dot<W: Write + ?Sized>(writer: &mut W, graphs: &[Graph]) -> std::io::Result<()> {
+    writeln!(writer, "digraph features {{")?;
+    writeln!(writer, "    rankdir=LR;")?;
+    writeln!(writer, "    node [fontname=\"monospace\"];")?;
+
+    for (i, graph) in graphs.iter().enumerate() {
+        writeln!(writer, "    subgraph cluster_{} {{", i)?;
+        writeln!(
+            writer,
+            "        label=\"{} {}\";",
+            escape_dot(&graph.name),
+            escape_dot(&graph.version)
+        )?;
+
+        for (j, node) in graph.nodes.iter().enumerate() {
+            let shape = match node.kind {
+                NodeKind::Feature => "box",
+                NodeKind::Dependency => "ellipse",
+                NodeKind::DependencyFeature => "note",
+            };
+            let style = if node.default {
+                ", style=filled, fillcolor=palegreen"
+            } else {
+                ""
+            };
+            let label = match node.kind {
+                NodeKind::Dependency => format!("dep:{}", node.label),
+                _ => node.label.clone(),
+            };
+            writeln!(
+                writer,
+                "        \"{}_{}\" [label=\"{}\", shape={}{}];",
+                i,
+                j,
+                escape_dot(&label),
+                shape,
+                style
+            )?;
+        }
+
+        for edge in &graph.edges {
+            let style = if edge.weak { " [style=dashed]" } else { "" };
+            writeln!(
+                writer,
+                "        \"{}_{}\" -> \"{}_{}\"{};",
+                i, edge.from, i, edge.to, style
+            )?;
+        }
+
+        writeln!(writer, "    }}")?;
+    }
+
+    writeln!(writer, "}}")
+}
+
+fn write_mermaid<W: Write + ?Sized>(writer: &mut W, graphs: &[Graph]) -> std::io::Result<()> {
+    writeln!(writer, "flowchart LR")?;
+    // `default` is a reserved class name in mermaid
+    writeln!(
+        writer,
+        "    classDef enabled_by_default fill:#9f9,stroke:#393"
+    )?;
+
+    for (i, graph) in graphs.iter().enumerate() {
+        writeln!(
+            writer,
+            "    subgraph c{}[\"{} {}\"]",
+            i,
+            escape_mermaid(&graph.name),
+            escape_mermaid(&graph.version)
+        )?;
+
+        for (j, node) in graph.nodes.iter().enumerate() {
+            let (open, close, label) = match node.kind {
+                NodeKind::Feature => ("[", "]", node.label.clone()),
+                NodeKind::Dependency => ("([", "])", format!("dep:{}", node.label)),
+                NodeKind::DependencyFeature => ("{{", "}}", node.label.clone()),
+            };
+            writeln!(
+                writer,
+                "        c{}_{}{}\"{}\"{}",
+                i,
+                j,
+                open,
+                escape_mermaid(&label),
+                close
+            )?;
+        }
+
+        for edge in &graph.edges {
+            let arrow = if edge.weak { "-.->" } else { "-->" };
+            writeln!(
+                writer,
+                "        c{}_{} {} c{}_{}",
+                i, edge.from, arrow, i, edge.to
+            )?;
+        }
+
+        writeln!(writer, "    end")?;
+
+        let default = graph
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.default)
+            .map(|(j, _)| format!("c{}_{}", i, j))
+            .collect::<Vec<_>>();
+        if !default.is_empty() {
+            writeln!(writer, "    class {} enabled_by_default", default.join(","))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_mermaid(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
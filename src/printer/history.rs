@@ -0,0 +1,82 @@
+use super::{
+    labels,
+    tree::{Node, Printer},
+};
+use crate::{
+    history::{EventKind, FeatureHistory},
+    Theme,
+};
+use std::io::Write;
+
+pub struct HistoryPrinter<'a, W: ?Sized> {
+    writer: &'a mut W,
+    theme: Theme,
+    history: &'a FeatureHistory,
+}
+
+impl<'a, W: ?Sized> HistoryPrinter<'a, W>
+where
+    W: Write,
+{
+    pub fn new(writer: &'a mut W, history: &'a FeatureHistory, theme: Theme) -> Self {
+        Self {
+            writer,
+            theme,
+            history,
+        }
+    }
+
+    pub fn print(self) -> std::io::Result<()> {
+        let Self {
+            writer,
+            theme,
+            history,
+        } = self;
+
+        // from_entries ensures there is at least one version
+        let (first, last) = (
+            &history.versions[0],
+            &history.versions[history.versions.len() - 1],
+        );
+        let header = format!(
+            "{} ({} versions, \"{}\" to \"{}\")",
+            theme.name.paint(&history.name),
+            history.versions.len(),
+            theme.version.paint(first),
+            theme.version.paint(last),
+        );
+
+        if history.features.is_empty() {
+            let node = Node::empty(theme.no_features.paint(labels::NO_FEATURES));
+            return Node::new(header, std::iter::once(node)).print(writer, &theme);
+        }
+
+        let nodes = history.features.iter().map(|(name, events)| {
+            let name = if name.starts_with('_') {
+                theme.probably_internal.paint(name)
+            } else {
+                theme.feature_name.paint(name)
+            };
+
+            let events = events.iter().map(|event| {
+                let (sign, color, label) = match event.kind {
+                    EventKind::Added => ("+", theme.added, labels::ADDED_IN),
+                    EventKind::Removed => ("-", theme.removed, labels::REMOVED_IN),
+                    EventKind::AddedToDefault => ("+", theme.default, labels::DEFAULT_SINCE),
+                    EventKind::RemovedFromDefault => {
+                        ("-", theme.default, labels::NOT_DEFAULT_SINCE)
+                    }
+                };
+                format!(
+                    "{} {} {}",
+                    color.paint(sign),
+                    label,
+                    theme.version.paint(&event.version)
+                )
+            });
+            Node::new(name, events)
+        });
+
+        Node::new(header, nodes).print(writer, &theme)
+    }
+}
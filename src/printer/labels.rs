@@ -16,5 +16,65 @@ pub const REQUIRED_DEPENDENCIES: &str = "required dependencies";
 
 pub const HAS_ENABLED_FEATURES: &str = "(has enabled features)";
 
+pub const IMPLICIT: &str = "implicit";
+pub const NO_IMPLICIT_FEATURE: &str = "(no implicit feature)";
+pub const NOT_FOR: &str = "not for";
+pub const DEPENDENCY: &str = "dependency";
+pub const DEPENDENCY_FEATURE: &str = "dependency feature";
+pub const WEAK_DEPENDENCY_FEATURE: &str = "weak dependency feature";
+
+pub const ENABLED_FEATURES: &str = "enabled features";
+pub const NO_ENABLED_FEATURES: &str = "no enabled features";
+pub const ACTIVATED_DEPENDENCIES: &str = "activated optional dependencies";
+pub const NO_ACTIVATED_DEPENDENCIES: &str = "no activated optional dependencies";
+pub const ENABLED_DEPENDENCY_FEATURES: &str = "enabled dependency features";
+pub const NO_ENABLED_DEPENDENCY_FEATURES: &str = "no enabled dependency features";
+pub const IS_ENABLED_BY: &str = "is enabled by";
+
+pub const NO_CHANGES: &str = "no changes";
+pub const NO_FEATURE_CHANGES: &str = "no feature changes";
+pub const NO_DEPENDENCY_CHANGES: &str = "no dependency changes";
+pub const DEPENDENCIES: &str = "dependencies";
+pub const RENAMED: &str = "renamed";
+
+pub const DISABLED_FEATURES: &str = "disabled features";
+pub const NO_DISABLED_FEATURES: &str = "no disabled features";
+pub const REQUESTED_BY: &str = "requested by";
+
+pub const ADDED_IN: &str = "added in";
+pub const REMOVED_IN: &str = "removed in";
+pub const DEFAULT_SINCE: &str = "enabled by default since";
+pub const NOT_DEFAULT_SINCE: &str = "not enabled by default since";
+pub const NOTHING_ENABLES: &str = "no other feature enables";
+
+pub const FEATURES_IN_SOURCE: &str = "features used in the source";
+pub const NO_FEATURES_IN_SOURCE: &str = "no features are used in the source";
+pub const UNDECLARED_FEATURES: &str =
+    "undeclared features (used in the source, but not in the Cargo.toml)";
+pub const NO_UNDECLARED_FEATURES: &str = "no undeclared features";
+pub const UNUSED_FEATURES: &str = "unused features (declared, but nothing in the source uses them)";
+pub const NO_UNUSED_FEATURES: &str = "no unused features";
+pub const WHEN_DISABLED: &str = "(when disabled)";
+
 pub const POSSIBLY_OLD_CRATE: &str =
     "this crate was from the cache and possibly isn't the latest version.";
+
+pub const DOCS_RS: &str = "docs.rs";
+pub const ALL_FEATURES: &str = "all features";
+pub const DEFAULT_FEATURES: &str = "default features";
+pub const DEFAULT_TARGET: &str = "default target";
+pub const TARGETS: &str = "targets";
+pub const RUSTC_ARGS: &str = "rustc args";
+pub const RUSTDOC_ARGS: &str = "rustdoc args";
+pub const CARGO_ARGS: &str = "cargo args";
+pub const UNDOCUMENTED_FEATURES: &str = "undocumented features (not enabled on docs.rs)";
+pub const NO_UNDOCUMENTED_FEATURES: &str = "every feature is documented";
+
+pub const PACKAGE: &str = "package";
+pub const RUST_VERSION: &str = "rust-version";
+pub const EDITION: &str = "edition";
+pub const LICENSE: &str = "license";
+pub const LINKS: &str = "links";
+pub const REPOSITORY: &str = "repository";
+pub const HOMEPAGE: &str = "homepage";
+pub const CATEGORIES: &str = "categories";
@@ -0,0 +1,280 @@
+use super::{
+    deps::{GroupedDeps, SortedDeps},
+    labels,
+};
+use crate::{
+    client::Version,
+    features::{Dependency, FeatureEntry, Features},
+    Target,
+};
+use std::{collections::BTreeSet, io::Write};
+
+/// Write each crate of a workspace, with a heading per crate
+pub(super) fn write_workspace<W: Write + ?Sized>(
+    writer: &mut W,
+    hint: Option<&str>,
+    list: &[&Features],
+    options: &super::Options,
+) -> std::io::Result<()> {
+    let level = match hint {
+        Some(hint) => {
+            writeln!(writer, "# workspace for {}", code(hint))?;
+            3
+        }
+        None => 2,
+    };
+
+    let list = list
+        .iter()
+        .filter(|features| features.published || options.show_private);
+    for (i, features) in list.enumerate() {
+        if hint.is_some() || i > 0 {
+            writeln!(writer)?;
+        }
+        write_crate(writer, features, options, level)?;
+    }
+    Ok(())
+}
+
+fn write_crate<W: Write + ?Sized>(
+    writer: &mut W,
+    features: &Features,
+    options: &super::Options,
+    level: usize,
+) -> std::io::Result<()> {
+    let heading = "#".repeat(level);
+    let restricted = if features.published {
+        ""
+    } else {
+        " (restricted)"
+    };
+    writeln!(
+        writer,
+        "{} {} {}{}",
+        heading, features.name, features.version, restricted
+    )?;
+
+    let sub_heading = "#".repeat(level + 1);
+
+    if options.print_features {
+        writeln!(writer)?;
+        writeln!(writer, "{} Features", sub_heading)?;
+        writeln!(writer)?;
+        write_features(writer, features)?;
+    }
+
+    let target = options.target.as_ref();
+
+    if options.verbose || (!options.print_features && options.show_deps) {
+        writeln!(writer)?;
+        writeln!(writer, "{} Optional dependencies", sub_heading)?;
+        writeln!(writer)?;
+
+        let sorted = SortedDeps::from_kind_map(features.optional_deps.clone());
+        if sorted.normal.has_deps() {
+            write_deps(writer, sorted.normal, Some(&features.hidden_deps), target)?;
+        } else {
+            writeln!(writer, "_{}_", labels::NO_OPTIONAL_DEPENDENCIES)?;
+        }
+    }
+
+    if options.show_deps {
+        writeln!(writer)?;
+        writeln!(writer, "{} Required dependencies", sub_heading)?;
+
+        let sorted = SortedDeps::from_kind_map(features.required_deps.clone());
+        let kinds = [
+            (
+                labels::NORMAL,
+                sorted.normal,
+                labels::NO_NORMAL_DEPENDENCIES,
+            ),
+            (
+                labels::DEVELOPMENT,
+                sorted.development,
+                labels::NO_DEVELOPMENT_DEPENDENCIES,
+            ),
+            (labels::BUILD, sorted.build, labels::NO_BUILD_DEPENDENCIES),
+        ];
+
+        for (name, deps, none) in kinds {
+            writeln!(writer)?;
+            writeln!(writer, "{}# {}", sub_heading, capitalize(name))?;
+            writeln!(writer)?;
+            if deps.has_deps() {
+                write_deps(writer, deps, None, target)?;
+            } else {
+                writeln!(writer, "_{}_", none)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_features<W: Write + ?Sized>(writer: &mut W, features: &Features) -> std::io::Result<()> {
+    if features.features.is_empty() {
+        return writeln!(writer, "_{}_", labels::NO_FEATURES);
+    }
+
+    let default = features
+        .features
+        .get("default")
+        .map(|entries| entries.iter().collect::<BTreeSet<_>>())
+        .unwrap_or_default();
+
+    let mut names = features.features.keys().collect::<Vec<_>>();
+    // default is always first
+    names.sort_by_key(|name| (*name != "default", *name));
+
+    writeln!(writer, "| Feature | Default | Enables |")?;
+    writeln!(writer, "| --- | --- | --- |")?;
+    for name in names {
+        let mut feature = code(name);
+        if features.implicit_features.contains(name) {
+            feature = format!("{} ({})", feature, labels::IMPLICIT);
+        }
+
+        let as_feature = FeatureEntry::Feature {
+            name: name.to_string(),
+        };
+        let is_default = if default.contains(&as_feature) {
+            "yes"
+        } else {
+            ""
+        };
+
+        let enables = features.features[name]
+            .iter()
+            .map(|entry| code(&entry.to_string()))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        writeln!(writer, "| {} | {} | {} |", feature, is_default, enables)?;
+    }
+    Ok(())
+}
+
+fn write_deps<W: Write + ?Sized>(
+    writer: &mut W,
+    deps: GroupedDeps,
+    hidden: Option<&BTreeSet<String>>,
+    current: Option<&Target>,
+) -> std::io::Result<()> {
+    writeln!(writer, "| Dependency | Version | Target | Features |")?;
+    writeln!(writer, "| --- | --- | --- | --- |")?;
+
+    let targets = deps
+        .with_targets
+        .into_iter()
+        .map(|(target, deps)| (Some(target), deps));
+
+    for (target, deps) in std::iter::once((None, deps.without_targets)).chain(targets) {
+        let target = match (target, current) {
+            (Some(target), Some(current)) if !current.matches(&target) => format!(
+                "{} ({} {})",
+                code(&target),
+                labels::NOT_FOR,
+                code(&current.triple)
+            ),
+            (Some(target), ..) => code(&target),
+            (None, ..) => String::new(),
+        };
+
+        for dep in deps {
+            writeln!(
+                writer,
+                "| {} | {} | {} | {} |",
+                format_dep(&dep, hidden),
+                code(&dep.req),
+                target,
+                dep.features
+                    .iter()
+                    .map(|s| code(s))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn format_dep(dep: &Dependency, hidden: Option<&BTreeSet<String>>) -> String {
+    let mut name = code(&dep.name);
+    if let Some(renamed) = dep.rename.as_deref() {
+        name = format!("{} (renamed to {})", name, code(renamed));
+    }
+    if hidden.filter(|hidden| hidden.contains(dep.key())).is_some() {
+        name = format!("{} {}", name, labels::NO_IMPLICIT_FEATURE);
+    }
+    name
+}
+
+/// Write a table of versions, with when they were published
+pub(super) fn write_versions<'a, W: Write + ?Sized>(
+    writer: &mut W,
+    versions: impl IntoIterator<Item = &'a Version>,
+    verbose: bool,
+) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "| Crate | Version | Published | Rust version | Yanked |"
+    )?;
+    writeln!(writer, "| --- | --- | --- | --- | --- |")?;
+    for version in versions {
+        let time = if !verbose {
+            version.format_approx_time_span()
+        } else {
+            version.format_verbose_time()
+        };
+
+        writeln!(
+            writer,
+            "| {} | {} | {} | {} | {} |",
+            code(&version.name),
+            code(&version.version),
+            time.unwrap_or_default(),
+            version.rust_version.as_deref().unwrap_or_default(),
+            if version.yanked { "yes" } else { "" }
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a table of crate names and versions
+pub(super) fn write_many_versions<W: Write + ?Sized>(
+    writer: &mut W,
+    list: Vec<(&String, &String, bool)>,
+    show_private: bool,
+) -> std::io::Result<()> {
+    writeln!(writer, "| Crate | Version |")?;
+    writeln!(writer, "| --- | --- |")?;
+    for (name, version, published) in list {
+        let restricted = match (published, show_private) {
+            (true, ..) => "",
+            (false, true) => " (restricted)",
+            (false, false) => continue,
+        };
+        writeln!(
+            writer,
+            "| {}{} | {} |",
+            code(name),
+            restricted,
+            code(version)
+        )?;
+    }
+    Ok(())
+}
+
+/// Wrap this in backticks, escaping anything that would break a table
+fn code(s: &str) -> String {
+    format!("`{}`", s.replace('|', "\\|"))
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|head| head.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
@@ -12,17 +12,41 @@ pub use theme::Theme;
 mod yank_status;
 pub use yank_status::YankStatus;
 
+mod format;
+pub use format::Format;
+
+mod graph;
+mod markdown;
+
 mod workspace;
 pub use workspace::WorkspacePrinter;
 
 mod version;
 pub use version::VersionPrinter;
 
-#[derive(Copy, Clone)]
+mod activation;
+pub use activation::ActivationPrinter;
+
+mod diff;
+pub use diff::DiffPrinter;
+
+mod history;
+pub use history::HistoryPrinter;
+
+mod scan;
+pub use scan::ScanPrinter;
+
+mod resolved;
+pub use resolved::{ResolvedPrinter, WhyPrinter};
+
+#[derive(Clone)]
 pub struct Options {
     pub print_features: bool,
     pub show_private: bool,
     pub show_deps: bool,
     pub verbose: bool,
     pub theme: Theme,
+    /// Target-specific dependencies that don't apply to this target are greyed out
+    pub target: Option<crate::Target>,
+    pub format: Format,
 }
@@ -0,0 +1,189 @@
+use super::{
+    labels,
+    tree::{Node, Printer},
+    workspace::format_dep,
+};
+use crate::{
+    features::Features,
+    resolve::{Resolved, Resolver, Why},
+    Theme,
+};
+use std::io::Write;
+
+pub struct ResolvedPrinter<'a, W: ?Sized> {
+    writer: &'a mut W,
+    theme: Theme,
+    features: &'a Features,
+    resolved: &'a Resolved,
+}
+
+impl<'a, W: ?Sized> ResolvedPrinter<'a, W>
+where
+    W: Write,
+{
+    pub fn new(
+        writer: &'a mut W,
+        features: &'a Features,
+        resolved: &'a Resolved,
+        theme: Theme,
+    ) -> Self {
+        Self {
+            writer,
+            theme,
+            features,
+            resolved,
+        }
+    }
+
+    pub fn print(self) -> std::io::Result<()> {
+        let Self {
+            writer,
+            theme,
+            features,
+            resolved,
+        } = self;
+
+        let header = format!(
+            "{} = \"{}\"",
+            theme.name.paint(&features.name),
+            theme.version.paint(&features.version),
+        );
+
+        let nodes = vec![
+            make_enabled_node(resolved, &theme),
+            make_activated_node(features, resolved, &theme),
+            make_dep_features_node(resolved, &theme),
+        ];
+
+        Node::new(header, nodes).print(writer, &theme)
+    }
+}
+
+pub struct WhyPrinter<'a, W: ?Sized> {
+    writer: &'a mut W,
+    theme: Theme,
+    features: &'a Features,
+    why: &'a Why,
+}
+
+impl<'a, W: ?Sized> WhyPrinter<'a, W>
+where
+    W: Write,
+{
+    pub fn new(writer: &'a mut W, features: &'a Features, why: &'a Why, theme: Theme) -> Self {
+        Self {
+            writer,
+            theme,
+            features,
+            why,
+        }
+    }
+
+    pub fn print(self) -> std::io::Result<()> {
+        let Self {
+            writer,
+            theme,
+            features,
+            why,
+        } = self;
+
+        let header = format!(
+            "{} = \"{}\"",
+            theme.name.paint(&features.name),
+            theme.version.paint(&features.version),
+        );
+
+        let node = if why.enabled_by.is_empty() {
+            Node::empty(format!(
+                "{} {}",
+                theme.no_features.paint(labels::NOTHING_ENABLES),
+                theme.feature_name.paint(&why.name)
+            ))
+        } else {
+            Node::new(
+                format!(
+                    "{} {}",
+                    theme.feature_name.paint(&why.name),
+                    theme.features.paint(labels::IS_ENABLED_BY)
+                ),
+                why.enabled_by.iter().map(|why| make_why_node(why, &theme)),
+            )
+        };
+
+        Node::new(header, std::iter::once(node)).print(writer, &theme)
+    }
+}
+
+fn make_why_node(why: &Why, theme: &Theme) -> Node {
+    let name = if why.name.starts_with('_') {
+        theme.probably_internal.paint(&why.name)
+    } else if why.name == "default" {
+        theme.default.paint(&why.name)
+    } else {
+        theme.feature_name.paint(&why.name)
+    };
+
+    Node::new(
+        name,
+        why.enabled_by.iter().map(|why| make_why_node(why, theme)),
+    )
+}
+
+fn make_enabled_node(resolved: &Resolved, theme: &Theme) -> Node {
+    if resolved.features.is_empty() {
+        return Node::empty(theme.no_features.paint(labels::NO_ENABLED_FEATURES));
+    }
+
+    let iter = resolved.features.iter().map(|name| {
+        if name.starts_with('_') {
+            theme.probably_internal.paint(name)
+        } else {
+            theme.feature_name.paint(name)
+        }
+    });
+    Node::new(theme.features.paint(labels::ENABLED_FEATURES), iter)
+}
+
+fn make_activated_node(features: &Features, resolved: &Resolved, theme: &Theme) -> Node {
+    if resolved.optional_deps.is_empty() {
+        let name = theme
+            .no_optional_deps
+            .paint(labels::NO_ACTIVATED_DEPENDENCIES);
+        return Node::empty(name);
+    }
+
+    let resolver = Resolver::new(features);
+    let iter = resolved
+        .optional_deps
+        .iter()
+        .filter_map(|name| resolver.optional_dep(name))
+        .map(|dep| format_dep(dep, theme));
+
+    Node::new(
+        theme.optional_deps.paint(labels::ACTIVATED_DEPENDENCIES),
+        iter,
+    )
+}
+
+fn make_dep_features_node(resolved: &Resolved, theme: &Theme) -> Node {
+    if resolved.dep_features.is_empty() {
+        let name = theme
+            .no_required_deps
+            .paint(labels::NO_ENABLED_DEPENDENCY_FEATURES);
+        return Node::empty(name);
+    }
+
+    let iter = resolved.dep_features.iter().map(|(dep, features)| {
+        Node::new(
+            theme.name.paint(dep),
+            features.iter().map(|s| theme.dep_feature.paint(s)),
+        )
+    });
+
+    Node::new(
+        theme
+            .has_enabled_features
+            .paint(labels::ENABLED_DEPENDENCY_FEATURES),
+        iter,
+    )
+}
@@ -0,0 +1,153 @@
+use super::{
+    labels,
+    tree::{Node, Printer},
+};
+use crate::{
+    scan::{FeatureUsage, SourceScan, UsageKind},
+    Theme,
+};
+use std::{collections::BTreeMap, io::Write, path::Path};
+
+pub struct ScanPrinter<'a, W: ?Sized> {
+    writer: &'a mut W,
+    theme: Theme,
+    scan: &'a SourceScan,
+    verbose: bool,
+}
+
+impl<'a, W: ?Sized> ScanPrinter<'a, W>
+where
+    W: Write,
+{
+    pub fn new(writer: &'a mut W, scan: &'a SourceScan, verbose: bool, theme: Theme) -> Self {
+        Self {
+            writer,
+            theme,
+            scan,
+            verbose,
+        }
+    }
+
+    pub fn print(self) -> std::io::Result<()> {
+        let Self {
+            writer,
+            theme,
+            scan,
+            verbose,
+        } = self;
+
+        let header = format!(
+            "{} = \"{}\"",
+            theme.name.paint(&scan.name),
+            theme.version.paint(&scan.version),
+        );
+
+        let used = if scan.usages.is_empty() {
+            Node::empty(theme.no_features.paint(labels::NO_FEATURES_IN_SOURCE))
+        } else {
+            let iter = scan.usages.iter().map(|(name, usages)| {
+                let text = format!(
+                    "{}: {}, {}",
+                    feature_name(name, &theme),
+                    plural(usages.len(), "place"),
+                    plural(scan.gated_lines(name), "line")
+                );
+                Node::new(text, make_file_nodes(usages, verbose, &theme))
+            });
+            Node::new(theme.features.paint(labels::FEATURES_IN_SOURCE), iter)
+        };
+
+        let undeclared = if scan.undeclared.is_empty() {
+            Node::empty(theme.no_features.paint(labels::NO_UNDECLARED_FEATURES))
+        } else {
+            let iter = scan
+                .undeclared
+                .iter()
+                .map(|name| theme.warning.paint(name).to_string());
+            Node::new(theme.features.paint(labels::UNDECLARED_FEATURES), iter)
+        };
+
+        let unused = if scan.unused.is_empty() {
+            Node::empty(theme.no_features.paint(labels::NO_UNUSED_FEATURES))
+        } else {
+            let iter = scan.unused.iter().map(|name| feature_name(name, &theme));
+            Node::new(theme.features.paint(labels::UNUSED_FEATURES), iter)
+        };
+
+        Node::new(header, vec![used, undeclared, unused]).print(writer, &theme)
+    }
+}
+
+fn make_file_nodes(usages: &[FeatureUsage], verbose: bool, theme: &Theme) -> Vec<Node> {
+    let mut files = BTreeMap::<&Path, Vec<&FeatureUsage>>::new();
+    for usage in usages {
+        files.entry(&usage.file).or_default().push(usage)
+    }
+
+    files
+        .into_iter()
+        .map(|(file, usages)| {
+            let lines = usages.iter().map(|usage| usage.lines).sum();
+            let text = format!(
+                "{}: {}, {}",
+                theme.target.paint(file.display()),
+                plural(usages.len(), "place"),
+                plural(lines, "line")
+            );
+            if !verbose {
+                return Node::empty(text);
+            }
+            Node::new(
+                text,
+                usages.iter().map(|usage| make_usage_node(usage, theme)),
+            )
+        })
+        .collect()
+}
+
+fn make_usage_node(usage: &FeatureUsage, theme: &Theme) -> Node {
+    let kind = match usage.kind {
+        UsageKind::Cfg => "#[cfg]",
+        UsageKind::InnerCfg => "#![cfg]",
+        UsageKind::CfgAttr => "#[cfg_attr]",
+        UsageKind::InnerCfgAttr => "#![cfg_attr]",
+        UsageKind::CfgMacro => "cfg!",
+    };
+
+    let mut text = format!("line {}: {}", usage.line, kind);
+    if let Some(item) = &usage.item {
+        text.push_str(&format!(" {}", theme.feature_implies.paint(item)));
+    }
+    if usage.lines > 0 {
+        text.push_str(&format!(" ({})", plural(usage.lines, "line")));
+    }
+    if usage.negated {
+        text.push_str(&format!(
+            " {}",
+            theme.dep_feature.paint(labels::WHEN_DISABLED)
+        ));
+    }
+
+    Node::new(
+        text,
+        usage
+            .files
+            .iter()
+            .map(|file| theme.target.paint(file.display()).to_string()),
+    )
+}
+
+fn feature_name(name: &str, theme: &Theme) -> String {
+    if name.starts_with('_') {
+        theme.probably_internal.paint(name).to_string()
+    } else {
+        theme.feature_name.paint(name).to_string()
+    }
+}
+
+fn plural(n: usize, word: &str) -> String {
+    match n {
+        1 => format!("1 {}", word),
+        n => format!("{} {}s", n, word),
+    }
+}
@@ -26,6 +26,7 @@ pub struct Theme {
     pub features: Color,
     pub feature_name: Color,
     pub feature_implies: Color,
+    pub feature_doc: Color,
 
     pub probably_internal: Color,
 
@@ -43,8 +44,13 @@ pub struct Theme {
     pub renamed_target: Color,
 
     pub target: Color,
+    pub other_target: Color,
     pub dep_feature: Color,
 
+    pub added: Color,
+    pub removed: Color,
+    pub changed: Color,
+
     pub tree: Color,
 }
 
@@ -107,6 +113,7 @@ const NO_THEME: Theme = Theme {
     features: Color::Unset,
     feature_name: Color::Unset,
     feature_implies: Color::Unset,
+    feature_doc: Color::Unset,
 
     probably_internal: Color::Unset,
 
@@ -124,8 +131,13 @@ const NO_THEME: Theme = Theme {
     renamed_target: Color::Unset,
 
     target: Color::Unset,
+    other_target: Color::Unset,
     dep_feature: Color::Unset,
 
+    added: Color::Unset,
+    removed: Color::Unset,
+    changed: Color::Unset,
+
     tree: Color::Unset,
 };
 
@@ -155,6 +167,7 @@ const DEFAULT_THEME: Theme = Theme {
     features: Color::RGB(192, 0, 192),
     feature_name: Color::RGB(255, 255, 255),
     feature_implies: Color::RGB(192, 192, 255),
+    feature_doc: Color::RGB(160, 160, 160),
 
     probably_internal: Color::RGB(128, 32, 32),
 
@@ -172,8 +185,13 @@ const DEFAULT_THEME: Theme = Theme {
     renamed_target: Color::RGB(92, 64, 255),
 
     target: Color::RGB(192, 0, 0),
+    other_target: Color::RGB(96, 96, 96),
     dep_feature: Color::RGB(128, 128, 128),
 
+    added: Color::RGB(0, 255, 0),
+    removed: Color::RGB(255, 0, 0),
+    changed: Color::RGB(255, 255, 0),
+
     tree: Color::RGB(48, 48, 48),
 };
 
@@ -188,9 +206,15 @@ const BASIC_THEME: Theme = Theme {
     yanked: Color::RGB(255, 0, 0),
 
     feature_implies: Color::RGB(192, 192, 255),
+    feature_doc: Color::RGB(160, 160, 160),
 
     default: Color::RGB(0, 255, 0),
 
+    other_target: Color::RGB(96, 96, 96),
+
+    added: Color::RGB(0, 255, 0),
+    removed: Color::RGB(255, 0, 0),
+
     tree: Color::RGB(48, 48, 48),
 
     ..NO_THEME
@@ -222,7 +246,13 @@ const PALETTE_THEME: Theme = Theme {
     renamed_target: Color::Yellow,
 
     target: Color::Yellow,
+    // bright black, the grey of the 16 color palette
+    other_target: Color::Fixed(8),
     dep_feature: Color::Green,
 
+    added: Color::Green,
+    removed: Color::Red,
+    changed: Color::Yellow,
+
     ..NO_THEME
 };
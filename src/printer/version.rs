@@ -1,4 +1,4 @@
-use crate::{client::Version, Options, Theme, YankStatus};
+use crate::{client::Version, Format, Options, Theme, YankStatus};
 use std::io::Write;
 
 /// Output for the program
@@ -26,12 +26,16 @@ impl<'a, W: Write + ?Sized> VersionPrinter<'a, W> {
         verbose: bool,
     ) -> std::io::Result<()> {
         use YankStatus::*;
+        let versions = versions.iter().filter(|ver| match (yank, ver.yanked) {
+            (Exclude, true) | (Only, false) => false,
+            _ => true,
+        });
+
+        if let Format::Markdown = self.options.format {
+            return super::markdown::write_versions(self.writer, versions, verbose);
+        }
+
         let output = versions
-            .iter()
-            .filter(|ver| match (yank, ver.yanked) {
-                (Exclude, true) | (Only, false) => false,
-                _ => true,
-            })
             .map(|version| match yank {
                 Exclude => self.write_latest(version, verbose),
                 Only => self.write_yanked(version, verbose),
@@ -50,14 +54,15 @@ impl<'a, W: Write + ?Sized> VersionPrinter<'a, W> {
 
         output
             .iter()
-            .map(|v| {
-                writeln!(
+            .map(|v| match &v.right {
+                Some(right) => writeln!(
                     self.writer,
                     "{}{} # {}",
                     v.left,
                     &padding[v.left_len..],
-                    v.right
-                )
+                    right
+                ),
+                None => writeln!(self.writer, "{}", v.left),
             })
             .collect()
     }
@@ -67,6 +72,11 @@ impl<'a, W: Write + ?Sized> VersionPrinter<'a, W> {
         &mut self,
         list: Vec<(&String, &String, bool)>,
     ) -> std::io::Result<()> {
+        if let Format::Markdown = self.options.format {
+            let show_private = self.options.show_private;
+            return super::markdown::write_many_versions(self.writer, list, show_private);
+        }
+
         let max = list.iter().map(|(s, ..)| s.len()).max().unwrap();
 
         let theme = self.theme;
@@ -101,8 +111,18 @@ impl<'a, W: Write + ?Sized> VersionPrinter<'a, W> {
         version: &Version,
         verbose: bool,
     ) -> std::io::Result<()> {
-        let VersionOutput { left, right, .. } = self.write_latest(version, verbose);
-        writeln!(self.writer, "{} # {}", left, right)
+        if let Format::Markdown = self.options.format {
+            return super::markdown::write_versions(self.writer, Some(version), verbose);
+        }
+
+        match self.write_latest(version, verbose) {
+            VersionOutput {
+                left,
+                right: Some(right),
+                ..
+            } => writeln!(self.writer, "{} # {}", left, right),
+            VersionOutput { left, .. } => writeln!(self.writer, "{}", left),
+        }
     }
 
     fn write_yanked(&mut self, version: &Version, verbose: bool) -> VersionOutput {
@@ -112,20 +132,22 @@ impl<'a, W: Write + ?Sized> VersionPrinter<'a, W> {
             self.theme.yanked.paint(&version.version),
         );
 
-        let right = if !verbose {
-            format!(
-                "{} -- yanked",
-                self.theme
-                    .created_at
-                    .paint(&version.format_approx_time_span()),
-            )
+        let time = if !verbose {
+            version.format_approx_time_span()
         } else {
-            format!(
-                "{} -- yanked",
-                self.theme.created_at.paint(&version.format_verbose_time()),
-            )
+            version.format_verbose_time()
         };
 
+        let right = match time {
+            Some(time) => format!("{} -- yanked", self.theme.created_at.paint(time)),
+            None => String::from("yanked"),
+        };
+        let right = match rust_version(version, verbose, &self.theme) {
+            Some(rust_version) => format!("{} -- {}", right, rust_version),
+            None => right,
+        }
+        .into();
+
         VersionOutput {
             left_len: version.name.len() + version.version.len() + 3,
             left,
@@ -140,18 +162,16 @@ impl<'a, W: Write + ?Sized> VersionPrinter<'a, W> {
             self.theme.version.paint(&version.version),
         );
 
-        let right = if !verbose {
-            format!(
-                "{}",
-                self.theme
-                    .created_at
-                    .paint(&version.format_approx_time_span()),
-            )
+        let time = if !verbose {
+            version.format_approx_time_span()
         } else {
-            format!(
-                "{}",
-                self.theme.created_at.paint(&version.format_verbose_time()),
-            )
+            version.format_verbose_time()
+        };
+
+        let time = time.map(|time| format!("{}", self.theme.created_at.paint(time)));
+        let right = match (time, rust_version(version, verbose, &self.theme)) {
+            (Some(time), Some(rust_version)) => Some(format!("{} -- {}", time, rust_version)),
+            (time, rust_version) => time.or(rust_version),
         };
 
         VersionOutput {
@@ -162,8 +182,14 @@ impl<'a, W: Write + ?Sized> VersionPrinter<'a, W> {
     }
 }
 
+/// The rust version the version requires, only shown when verbose
+fn rust_version(version: &Version, verbose: bool, theme: &Theme) -> Option<String> {
+    let rust_version = version.rust_version.as_deref().filter(|_| verbose)?;
+    Some(format!("rust {}", theme.license.paint(rust_version)))
+}
+
 pub struct VersionOutput {
     left_len: usize,
     left: String,
-    right: String,
+    right: Option<String>,
 }
@@ -4,8 +4,8 @@ use super::{
     tree::{Node, Printer},
 };
 use crate::{
-    features::{Dependency, Features, Workspace},
-    Options, Theme,
+    features::{Dependency, FeatureEntry, Features, Workspace},
+    Format, Options, Target, Theme,
 };
 use std::{
     collections::{BTreeMap, BTreeSet},
@@ -44,7 +44,29 @@ where
 
         let (options, theme) = (self.options, self.theme);
 
-        let mut nodes = list.iter().filter_map(|f| make_child_node(f, &options));
+        match options.format {
+            Format::Markdown => {
+                let hint = Some(self.workspace.hint.as_str()).filter(|_| list.len() > 1);
+                let list = list.iter().collect::<Vec<_>>();
+                return super::markdown::write_workspace(self.writer, hint, &list, &options);
+            }
+            Format::Dot | Format::Mermaid => {
+                let list = list.iter().collect::<Vec<_>>();
+                return super::graph::write_graph(self.writer, &list, &options);
+            }
+            Format::Text | Format::Json => {}
+        }
+
+        // only run rustc if there is something to compare it to
+        let toolchain = list
+            .iter()
+            .any(|features| features.rust_version.is_some())
+            .then(crate::util::rustc_version)
+            .flatten();
+
+        let mut nodes = list
+            .iter()
+            .filter_map(|f| make_child_node(f, &options, toolchain.as_ref()));
 
         match list.len() {
             0 => unreachable!("empty tree"),
@@ -70,14 +92,21 @@ where
     }
 }
 
-fn make_child_node(features: &Features, options: &Options) -> Option<Node> {
+fn make_child_node(
+    features: &Features,
+    options: &Options,
+    toolchain: Option<&semver::Version>,
+) -> Option<Node> {
     let Options {
         print_features, // not -n
         show_deps,      // -d
         verbose,
         show_private,
         theme,
+        ref target,
+        ..
     } = *options;
+    let target = target.as_ref();
 
     if !features.published && !show_private {
         return None;
@@ -100,25 +129,44 @@ fn make_child_node(features: &Features, options: &Options) -> Option<Node> {
 
     let mut parent = Node::empty(header);
 
+    if let Some(node) = make_rust_version_warning(features, &theme, toolchain) {
+        parent.add_child(node);
+    }
+
+    if verbose {
+        if let Some(node) = make_package_node(features, &theme) {
+            parent.add_child(node);
+        }
+    }
+
     if print_features {
         let node = make_features_node(features, &theme, verbose);
         parent.add_child(node);
+
+        if let Some(node) = make_docs_rs_node(features, &theme, verbose) {
+            parent.add_child(node);
+        }
     }
 
     if verbose || (!print_features && show_deps) {
-        let node = make_opt_deps_node(features, &theme, verbose);
+        let node = make_opt_deps_node(features, &theme, verbose, target);
         parent.add_child(node);
     }
 
     if show_deps {
-        let node = make_deps_node(features, &theme, verbose);
+        let node = make_deps_node(features, &theme, verbose, target);
         parent.add_child(node)
     }
 
     Some(parent)
 }
 
-fn make_opt_deps_node(features: &Features, theme: &Theme, verbose: bool) -> Node {
+fn make_opt_deps_node(
+    features: &Features,
+    theme: &Theme,
+    verbose: bool,
+    target: Option<&Target>,
+) -> Node {
     let sorted = SortedDeps::from_kind_map(features.optional_deps.clone());
     if !sorted.normal.has_deps() {
         let name = theme
@@ -132,10 +180,17 @@ fn make_opt_deps_node(features: &Features, theme: &Theme, verbose: bool) -> Node
         sorted.normal,
         theme,
         verbose,
+        Some(&features.hidden_deps),
+        target,
     )
 }
 
-fn make_deps_node(features: &Features, theme: &Theme, verbose: bool) -> Node {
+fn make_deps_node(
+    features: &Features,
+    theme: &Theme,
+    verbose: bool,
+    target: Option<&Target>,
+) -> Node {
     let sorted = SortedDeps::from_kind_map(features.required_deps.clone());
     if !sorted.normal.has_deps() && !sorted.development.has_deps() && !sorted.build.has_deps() {
         return Node::empty(
@@ -152,6 +207,8 @@ fn make_deps_node(features: &Features, theme: &Theme, verbose: bool) -> Node {
             sorted.normal,
             theme,
             verbose,
+            None,
+            target,
         ));
     } else {
         let name = theme.no_required_deps.paint(labels::NO_NORMAL_DEPENDENCIES);
@@ -164,6 +221,8 @@ fn make_deps_node(features: &Features, theme: &Theme, verbose: bool) -> Node {
             sorted.development,
             theme,
             verbose,
+            None,
+            target,
         ));
     } else {
         let name = theme.no_dev_deps.paint(labels::NO_DEVELOPMENT_DEPENDENCIES);
@@ -176,6 +235,8 @@ fn make_deps_node(features: &Features, theme: &Theme, verbose: bool) -> Node {
             sorted.build,
             theme,
             verbose,
+            None,
+            target,
         ));
     } else {
         let name = theme.no_build_deps.paint(labels::NO_BUILD_DEPENDENCIES);
@@ -201,9 +262,16 @@ fn make_features_node(features: &Features, theme: &Theme, verbose: bool) -> Node
 
     let default_node = match sorted.remove(&"default".to_string()) {
         Some(def) if !def.is_empty() => {
+            let (name, docs) = with_docs(
+                theme.default.paint(labels::DEFAULT).to_string(),
+                features.docs.get("default"),
+                verbose,
+                theme,
+            );
             let node = Node::new(
-                theme.default.paint(labels::DEFAULT),
-                def.iter().map(|s| theme.feature_implies.paint(s)),
+                name,
+                docs.into_iter()
+                    .chain(def.iter().map(|entry| format_entry(entry, theme))),
             );
             default.replace(def);
             node
@@ -212,35 +280,38 @@ fn make_features_node(features: &Features, theme: &Theme, verbose: bool) -> Node
     };
 
     let iter = sorted.iter().map(|(k, v)| {
-        let (probably, other, features);
-        let k: &dyn std::fmt::Display = if k.starts_with('_') {
-            probably = theme.probably_internal.paint(k);
-            &probably
-        } else if default.as_ref().filter(|def| def.contains(k)).is_some() {
-            other = format!(
-                "{} ({})",
-                theme.feature_name.paint(k),
-                theme.default.paint("default")
-            );
-            &other
+        let mut name = if k.starts_with('_') {
+            theme.probably_internal.paint(k).to_string()
         } else {
-            features = theme.feature_name.paint(k);
-            &features
+            theme.feature_name.paint(k).to_string()
+        };
+
+        let as_feature = FeatureEntry::Feature {
+            name: k.to_string(),
         };
+        if default
+            .as_ref()
+            .filter(|def| def.contains(&as_feature))
+            .is_some()
+        {
+            name = format!("{} ({})", name, theme.default.paint(labels::DEFAULT));
+        }
+
+        if features.implicit_features.contains(*k) {
+            name = format!("{} ({})", name, theme.renamed.paint(labels::IMPLICIT));
+        }
 
-        if v.is_empty() || !verbose {
-            return Node::empty(k);
+        let (name, docs) = with_docs(name, features.docs.get(*k), verbose, theme);
+
+        if (v.is_empty() || !verbose) && docs.is_empty() {
+            return Node::empty(name);
         }
 
-        let children = v.iter().map(|s| {
-            let color = if s.starts_with('_') {
-                theme.probably_internal
-            } else {
-                theme.feature_implies
-            };
-            color.paint(s)
-        });
-        Node::new(k, children)
+        let entries = v.iter().filter(|_| verbose);
+        let children = docs
+            .into_iter()
+            .chain(entries.map(|entry| format_entry(entry, theme)));
+        Node::new(name, children)
     });
 
     Node::new(
@@ -249,24 +320,51 @@ fn make_features_node(features: &Features, theme: &Theme, verbose: bool) -> Node
     )
 }
 
+/// Format a feature entry, labelled with what kind of entry it is
+fn format_entry(entry: &FeatureEntry, theme: &Theme) -> String {
+    let label = match entry {
+        FeatureEntry::Feature { name } if name.starts_with('_') => {
+            return theme.probably_internal.paint(entry).to_string()
+        }
+        FeatureEntry::Feature { .. } => return theme.feature_implies.paint(entry).to_string(),
+        FeatureEntry::Dependency { .. } => labels::DEPENDENCY,
+        FeatureEntry::DependencyFeature { .. } => labels::DEPENDENCY_FEATURE,
+        FeatureEntry::WeakDependencyFeature { .. } => labels::WEAK_DEPENDENCY_FEATURE,
+    };
+
+    format!(
+        "{} ({})",
+        theme.dep_feature.paint(entry),
+        theme.renamed.paint(label)
+    )
+}
+
 fn build_features_tree(
     text: impl ToString,
     deps: GroupedDeps,
     theme: &Theme,
     verbose: bool,
+    hidden: Option<&BTreeSet<String>>,
+    current: Option<&Target>,
 ) -> Node {
-    let format = |(target, deps)| {
-        Node::new(
+    let format = |(target, deps): (String, _)| match current {
+        Some(current) if !current.matches(&target) => {
+            let name = format!("for {} ({} {})", target, labels::NOT_FOR, current.triple);
+            let greyed = greyed(theme);
+            let children = build_features(deps, &greyed, verbose, hidden);
+            Node::new(theme.other_target.paint(name), children)
+        }
+        _ => Node::new(
             format!("for {}", theme.target.paint(target)),
-            build_features(deps, theme, verbose),
-        )
+            build_features(deps, theme, verbose, hidden),
+        ),
     };
 
     let iter = deps
         .with_targets
         .into_iter()
         .map(format)
-        .chain(build_features(deps.without_targets, theme, verbose));
+        .chain(build_features(deps.without_targets, theme, verbose, hidden));
 
     Node::new(text, iter)
 }
@@ -275,9 +373,15 @@ fn build_features<'a>(
     deps: impl IntoIterator<Item = Dependency> + 'a,
     theme: &'a Theme,
     verbose: bool,
+    hidden: Option<&'a BTreeSet<String>>,
 ) -> impl Iterator<Item = Node> + 'a {
-    let map = move |dep| {
-        let name = format_dep(&dep, theme);
+    let map = move |dep: Dependency| {
+        let mut name = format_dep(&dep, theme);
+        if hidden.filter(|hidden| hidden.contains(dep.key())).is_some() {
+            let label = theme.renamed.paint(labels::NO_IMPLICIT_FEATURE);
+            name = format!("{}{}", name, label);
+        }
+
         if dep.features.is_empty() {
             return Node::empty(name);
         }
@@ -299,7 +403,165 @@ fn build_features<'a>(
     deps.into_iter().map(map)
 }
 
-fn format_dep(dep: &Dependency, theme: &Theme) -> String {
+fn make_rust_version_warning(
+    features: &Features,
+    theme: &Theme,
+    toolchain: Option<&semver::Version>,
+) -> Option<Node> {
+    let rust_version = features.rust_version.as_deref()?;
+    let required = crate::util::parse_rust_version(rust_version)?;
+    let toolchain = toolchain?;
+    if required <= *toolchain {
+        return None;
+    }
+
+    let text = format!(
+        "requires rust {}, but the rustc on the PATH is {}",
+        rust_version, toolchain
+    );
+    Some(Node::empty(theme.warning.paint(text)))
+}
+
+fn make_package_node(features: &Features, theme: &Theme) -> Option<Node> {
+    let mut children = vec![];
+    if let Some(description) = &features.description {
+        children.push(description.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+
+    let fields = [
+        (labels::RUST_VERSION, &features.rust_version),
+        (labels::EDITION, &features.edition),
+        (labels::LICENSE, &features.license),
+        (labels::LINKS, &features.links),
+        (labels::REPOSITORY, &features.repository),
+        (labels::HOMEPAGE, &features.homepage),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            children.push(format!("{}: {}", label, theme.version.paint(value)));
+        }
+    }
+
+    if !features.categories.is_empty() {
+        let categories = features.categories.join(", ");
+        children.push(format!(
+            "{}: {}",
+            labels::CATEGORIES,
+            theme.version.paint(categories)
+        ));
+    }
+
+    if children.is_empty() {
+        return None;
+    }
+    Some(Node::new(theme.workspace.paint(labels::PACKAGE), children))
+}
+
+fn make_docs_rs_node(features: &Features, theme: &Theme, verbose: bool) -> Option<Node> {
+    let docs_rs = features.docs_rs.as_ref()?;
+    let undocumented = features.undocumented_features()?;
+    // without -v, this is only shown when it is missing something
+    if !verbose && undocumented.is_empty() {
+        return None;
+    }
+
+    let mut children = vec![];
+    if docs_rs.all_features {
+        children.push(Node::empty(theme.default.paint(labels::ALL_FEATURES)));
+    } else {
+        if !docs_rs.no_default_features {
+            children.push(Node::empty(theme.default.paint(labels::DEFAULT_FEATURES)));
+        }
+        if !docs_rs.features.is_empty() {
+            let iter = docs_rs
+                .features
+                .iter()
+                .map(|name| theme.feature_name.paint(name).to_string());
+            children.push(Node::new(theme.features.paint(labels::FEATURES), iter));
+        }
+    }
+
+    if let Some(target) = &docs_rs.default_target {
+        let text = format!("{}: {}", labels::DEFAULT_TARGET, theme.target.paint(target));
+        children.push(Node::empty(text));
+    }
+
+    if let Some(targets) = &docs_rs.targets {
+        let iter = targets
+            .iter()
+            .map(|target| theme.target.paint(target).to_string());
+        children.push(Node::new(labels::TARGETS, iter));
+    }
+
+    if verbose {
+        let args = [
+            (labels::RUSTC_ARGS, &docs_rs.rustc_args),
+            (labels::RUSTDOC_ARGS, &docs_rs.rustdoc_args),
+            (labels::CARGO_ARGS, &docs_rs.cargo_args),
+        ];
+        for (label, args) in args.into_iter().filter(|(_, args)| !args.is_empty()) {
+            children.push(Node::empty(format!("{}: {}", label, args.join(" "))));
+        }
+    }
+
+    if undocumented.is_empty() {
+        children.push(Node::empty(
+            theme.no_features.paint(labels::NO_UNDOCUMENTED_FEATURES),
+        ));
+    } else {
+        let iter = undocumented
+            .iter()
+            .map(|name| theme.warning.paint(name).to_string());
+        children.push(Node::new(
+            theme.features.paint(labels::UNDOCUMENTED_FEATURES),
+            iter,
+        ));
+    }
+
+    Some(Node::new(theme.workspace.paint(labels::DOCS_RS), children))
+}
+
+/// Adds the first line of a feature's documentation to its name, or every line of it when verbose
+fn with_docs(
+    name: String,
+    docs: Option<&String>,
+    verbose: bool,
+    theme: &Theme,
+) -> (String, Vec<String>) {
+    let mut lines = docs
+        .into_iter()
+        .flat_map(|docs| docs.lines())
+        .filter(|line| !line.is_empty());
+
+    if verbose {
+        let lines = lines.map(|line| theme.feature_doc.paint(line).to_string());
+        return (name, lines.collect());
+    }
+
+    match lines.next() {
+        Some(line) => (
+            format!("{}: {}", name, theme.feature_doc.paint(line)),
+            vec![],
+        ),
+        None => (name, vec![]),
+    }
+}
+
+/// A theme that paints every part of a dependency in the color for other targets
+fn greyed(theme: &Theme) -> Theme {
+    let color = theme.other_target;
+    Theme {
+        name: color,
+        version: color,
+        renamed: color,
+        renamed_target: color,
+        has_enabled_features: color,
+        dep_feature: color,
+        ..*theme
+    }
+}
+
+pub(super) fn format_dep(dep: &Dependency, theme: &Theme) -> String {
     if let Some(renamed) = dep.rename.as_deref() {
         let renamed = format!("(renamed to {})", theme.renamed_target.paint(renamed));
         return format!(
@@ -1,17 +1,24 @@
 use anyhow::Context as _;
 use std::{collections::HashSet, path::PathBuf};
 
-use crate::features::Workspace;
+use crate::{cargo_config::RegistryConfig, features::Workspace};
 
 /// Local disk registry (cargo and our own)
 pub struct Registry {
     cached: HashSet<Crate>,
     local: HashSet<Crate>,
+    archives: HashSet<Crate>,
+    cache_dir: Option<PathBuf>,
 }
 
 impl Registry {
-    /// Create a registry from the local cache (cargos and ours)
+    /// Create a registry from the local cache (cargos and ours) for crates.io
     pub fn from_local() -> anyhow::Result<Self> {
+        Self::for_registry(&RegistryConfig::crates_io())
+    }
+
+    /// Create a registry from the local cache (cargos and ours) for this remote registry
+    pub fn for_registry(registry: &RegistryConfig) -> anyhow::Result<Self> {
         use crate_version_parse::CrateVersion;
 
         // TODO use jwalk here
@@ -24,7 +31,14 @@ impl Registry {
         let (mut set, mut local) = (HashSet::new(), HashSet::new());
 
         for path in home
-            .filter_map(|dir| dir.ok()?.path().read_dir().ok())
+            .filter_map(|dir| {
+                let dir = dir.ok()?;
+                let name = dir.file_name();
+                if !registry.owns_cargo_dir(name.to_str()?) {
+                    return None;
+                }
+                dir.path().read_dir().ok()
+            })
             .flat_map(|dir| dir.flatten())
             .map(|s| s.path())
         {
@@ -40,7 +54,7 @@ impl Registry {
         }
 
         // TODO this should probably be a warning at the least
-        if let Ok(base) = crate::util::cache_dir() {
+        if let Ok(base) = registry.cache_dir() {
             // TODO use jwalk here
             for dir in base
                 .read_dir()
@@ -48,7 +62,7 @@ impl Registry {
                 .flat_map(|dir| dir.flatten())
                 .filter_map(|dir| {
                     let path = dir.path();
-                    if !path.is_dir() {
+                    if !path.is_dir() || dir.file_name() == crate::util::REGISTRIES_DIR {
                         return None;
                     }
                     path.into()
@@ -72,24 +86,120 @@ impl Registry {
             }
         }
 
-        Ok(Self { cached: set, local })
+        // the .crate files that cargo downloaded, but hasn't unpacked
+        let mut archives = HashSet::new();
+        for path in home::cargo_home()?
+            .join("registry")
+            .join("cache")
+            .read_dir()
+            .into_iter()
+            .flat_map(|dir| dir.flatten())
+            .filter_map(|dir| {
+                let name = dir.file_name();
+                if !registry.owns_cargo_dir(name.to_str()?) {
+                    return None;
+                }
+                dir.path().read_dir().ok()
+            })
+            .flat_map(|dir| dir.flatten())
+            .map(|s| s.path())
+            .filter(|path| path.extension().filter(|&ext| ext == "crate").is_some())
+        {
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                let CrateVersion { name, version } = CrateVersion::try_parse(name)?;
+                let crate_ = Crate {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    path,
+                    yanked: YankState::UnknownLocal,
+                };
+                if !set.contains(&crate_) && !local.contains(&crate_) {
+                    archives.insert(crate_);
+                }
+            }
+        }
+
+        Ok(Self {
+            cached: set,
+            local,
+            archives,
+            cache_dir: registry.cache_dir().ok(),
+        })
     }
 
     /// Tries to get the crate/version from the registry
-    pub fn get(&self, crate_name: &str, crate_version: &str) -> Option<&Crate> {
-        self.cached
-            .iter()
-            .chain(self.local.iter())
+    ///
+    /// If cargo has only downloaded the `.crate` file for it, it'll be unpacked into our cache
+    pub fn get(&self, crate_name: &str, crate_version: &str) -> anyhow::Result<Option<Crate>> {
+        self.all()
             .find(|Crate { name, version, .. }| name == crate_name && version == crate_version)
+            .map(|crate_| self.unpack(crate_))
+            .transpose()
+    }
+
+    /// Tries to get the newest crate from the registry that matches the version requirement
+    ///
+    /// If cargo has only downloaded the `.crate` file for it, it'll be unpacked into our cache
+    pub fn maybe_matching(&self, crate_name: &str, semver: &str) -> anyhow::Result<Option<Crate>> {
+        crate::util::find_matching(
+            self.all().filter(|Crate { name, .. }| name == crate_name),
+            |crate_| &crate_.version,
+            semver,
+        )
+        .map(|crate_| self.unpack(crate_))
+        .transpose()
     }
 
-    /// Tries to the the latest version from the cached registry
-    pub fn maybe_latest(&self, crate_name: &str) -> Option<&Crate> {
+    /// Tries to the the latest version from the cached registry, by semver precedence
+    ///
+    /// Pre-releases are only considered if `pre_release` is set, or if there are no other versions.
+    ///
+    /// If cargo has only downloaded the `.crate` file for it, it'll be unpacked into our cache
+    pub fn maybe_latest(
+        &self,
+        crate_name: &str,
+        pre_release: bool,
+    ) -> anyhow::Result<Option<Crate>> {
+        crate::util::find_latest(
+            self.all().filter(|Crate { name, .. }| name == crate_name),
+            |crate_| &crate_.version,
+            pre_release,
+        )
+        .map(|crate_| self.unpack(crate_))
+        .transpose()
+    }
+
+    fn all(&self) -> impl Iterator<Item = &Crate> {
         self.cached
             .iter()
             .chain(self.local.iter())
-            .filter(|Crate { name, .. }| name == crate_name)
-            .max_by(|Crate { version: left, .. }, Crate { version: right, .. }| left.cmp(&right))
+            .chain(self.archives.iter())
+    }
+
+    fn unpack(&self, crate_: &Crate) -> anyhow::Result<Crate> {
+        if !self.archives.contains(crate_) {
+            // only our own cache tracks when a crate was used
+            if self.local.contains(crate_) {
+                crate::cache::Cache::used(&crate_.path);
+            }
+            return Ok(crate_.clone());
+        }
+
+        let base = self
+            .cache_dir
+            .as_deref()
+            .with_context(|| "cannot open the cache directory")?;
+
+        let data = std::fs::read(&crate_.path)
+            .with_context(|| anyhow::anyhow!("cannot read {}", crate_.path.display()))?;
+
+        let path = crate::util::extract_crate(base, &data, &crate_.name, &crate_.version)?;
+        crate::cache::Cache::used(&path);
+
+        Ok(Crate {
+            path,
+            ..crate_.clone()
+        })
     }
 
     /// Purge the local cache, returning how many crates it removed
@@ -101,6 +211,24 @@ impl Registry {
         }
         Ok(count)
     }
+
+    /// Purge the local cache for crates.io and every other registry, returning how many crates it removed
+    pub fn purge_all_caches() -> anyhow::Result<usize> {
+        let mut count = Self::from_local()?.purge_local_cache()?;
+
+        let registries = crate::util::cache_dir()?.join(crate::util::REGISTRIES_DIR);
+        for dir in registries
+            .read_dir()
+            .into_iter()
+            .flat_map(|dir| dir.flatten())
+            .flat_map(|dir| dir.path().read_dir().into_iter().flatten().flatten())
+        {
+            std::fs::remove_dir_all(dir.path())?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
 }
 
 /// Whether this crate was marked as yanked on crates.io
@@ -0,0 +1,419 @@
+use crate::features::{Dependency, FeatureEntry, Features};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The result of resolving a set of features for a crate
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Resolved {
+    /// Every feature that ends up enabled
+    pub features: BTreeSet<String>,
+    /// The optional dependencies that are activated, by the name the crate refers to them as
+    pub optional_deps: BTreeSet<String>,
+    /// The features enabled on each dependency, by the name the crate refers to them as
+    pub dep_features: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// The features that enable a feature or an optional dependency
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Why {
+    /// The name of the feature (or the optional dependency, for the root)
+    pub name: String,
+    /// Every feature that directly enables this one
+    pub enabled_by: Vec<Why>,
+}
+
+impl Why {
+    /// Every path from a feature that nothing else enables, down to the root
+    pub fn paths(&self) -> Vec<Vec<String>> {
+        if self.enabled_by.is_empty() {
+            return vec![vec![self.name.clone()]];
+        }
+
+        self.enabled_by
+            .iter()
+            .flat_map(Self::paths)
+            .map(|mut path| {
+                path.push(self.name.clone());
+                path
+            })
+            .collect()
+    }
+}
+
+/// Computes the transitive closure of a set of features, the same way cargo does
+///
+/// This follows:
+/// * `feature` -- enables the feature (or the optional dependency, if there is no feature by that name)
+/// * `dep:name` -- activates the optional dependency, without enabling a feature
+/// * `name/feature` -- activates the dependency and enables the feature on it
+/// * `name?/feature` -- enables the feature on the dependency, only if something else activated it
+pub struct Resolver<'a> {
+    features: &'a Features,
+    resolved: Resolved,
+    weak: Vec<(String, String)>,
+}
+
+impl<'a> Resolver<'a> {
+    /// Create a resolver for this crate
+    pub fn new(features: &'a Features) -> Self {
+        Self {
+            features,
+            resolved: Resolved::default(),
+            weak: vec![],
+        }
+    }
+
+    /// Resolve these features
+    ///
+    /// Each one can be a feature name, an optional dependency or any `dep:`/`name/feature` entry.
+    /// `default` has to be provided if the default features should be enabled
+    pub fn resolve<I, S>(mut self, enabled: I) -> anyhow::Result<Resolved>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for entry in enabled {
+            let entry = FeatureEntry::parse(entry.as_ref());
+            self.verify(&entry)?;
+            self.enable(&entry);
+        }
+
+        for (dep, feature) in std::mem::take(&mut self.weak) {
+            if self.resolved.optional_deps.contains(&dep) || self.is_required(&dep) {
+                self.add_dep_feature(&dep, &feature);
+            }
+        }
+
+        Ok(self.resolved)
+    }
+
+    /// Find every feature that (transitively) enables this feature or optional dependency
+    pub fn why(&self, target: &str) -> anyhow::Result<Why> {
+        anyhow::ensure!(
+            self.features.features.contains_key(target) || self.optional_dep(target).is_some(),
+            "{} = {} has no feature or optional dependency named '{}'",
+            self.features.name,
+            self.features.version,
+            target
+        );
+
+        Ok(self.enabled_by(target, &mut vec![]))
+    }
+
+    fn enabled_by(&self, target: &str, seen: &mut Vec<String>) -> Why {
+        seen.push(target.to_string());
+
+        let mut names = self
+            .features
+            .features
+            .iter()
+            .filter(|(name, _)| !seen.contains(name))
+            .filter(|(_, entries)| entries.iter().any(|entry| self.activates(entry, target)))
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+
+        let enabled_by = names
+            .into_iter()
+            .map(|name| self.enabled_by(name, seen))
+            .collect();
+
+        seen.pop();
+        Why {
+            name: target.to_string(),
+            enabled_by,
+        }
+    }
+
+    /// Whether this feature entry activates the target feature or optional dependency
+    ///
+    /// Weak (`name?/feature`) entries never activate anything
+    fn activates(&self, entry: &FeatureEntry, target: &str) -> bool {
+        match entry {
+            FeatureEntry::Feature { name } => name == target,
+            FeatureEntry::Dependency { name: dep }
+            | FeatureEntry::DependencyFeature { dep, .. } => {
+                dep == target && self.optional_dep(target).is_some()
+            }
+            FeatureEntry::WeakDependencyFeature { .. } => false,
+        }
+    }
+
+    /// Find the optional dependency that is referred to by this name
+    pub fn optional_dep(&self, name: &str) -> Option<&'a Dependency> {
+        find_dep(self.features.optional_deps.values().flatten(), name)
+    }
+
+    fn is_required(&self, name: &str) -> bool {
+        find_dep(self.features.required_deps.values().flatten(), name).is_some()
+    }
+
+    fn verify(&self, entry: &FeatureEntry) -> anyhow::Result<()> {
+        let name = match entry {
+            FeatureEntry::Feature { name } if self.features.features.contains_key(name) => {
+                return Ok(())
+            }
+            FeatureEntry::Feature { name } | FeatureEntry::Dependency { name } => {
+                anyhow::ensure!(
+                    self.optional_dep(name).is_some(),
+                    "{} = {} has no feature or optional dependency named '{}'",
+                    self.features.name,
+                    self.features.version,
+                    name
+                );
+                return Ok(());
+            }
+            FeatureEntry::DependencyFeature { dep, .. }
+            | FeatureEntry::WeakDependencyFeature { dep, .. } => dep,
+        };
+
+        anyhow::ensure!(
+            self.optional_dep(name).is_some() || self.is_required(name),
+            "{} = {} has no dependency named '{}'",
+            self.features.name,
+            self.features.version,
+            name
+        );
+        Ok(())
+    }
+
+    fn enable(&mut self, entry: &FeatureEntry) {
+        match entry {
+            FeatureEntry::Feature { name } => self.enable_feature(name),
+            FeatureEntry::Dependency { name } => self.activate(name),
+            FeatureEntry::DependencyFeature { dep, feature } => {
+                if self.optional_dep(dep).is_some() {
+                    // this also enables the implicit feature for the dependency, if it has one
+                    if self.features.implicit_features.contains(dep) {
+                        self.enable_feature(dep);
+                    }
+                    self.activate(dep);
+                }
+                self.add_dep_feature(dep, feature);
+            }
+            FeatureEntry::WeakDependencyFeature { dep, feature } => {
+                self.weak.push((dep.clone(), feature.clone()))
+            }
+        }
+    }
+
+    fn enable_feature(&mut self, name: &str) {
+        if !self.resolved.features.insert(name.to_string()) {
+            return;
+        }
+
+        match self.features.features.get(name) {
+            Some(entries) => {
+                for entry in entries {
+                    self.enable(entry)
+                }
+            }
+            // older crates don't have implicit features for their optional dependencies
+            None => self.activate(name),
+        }
+    }
+
+    fn activate(&mut self, dep: &str) {
+        if self.optional_dep(dep).is_some() {
+            self.resolved.optional_deps.insert(dep.to_string());
+        }
+    }
+
+    fn add_dep_feature(&mut self, dep: &str, feature: &str) {
+        self.resolved
+            .dep_features
+            .entry(dep.to_string())
+            .or_default()
+            .insert(feature.to_string());
+    }
+}
+
+/// Find a dependency by the name its crate refers to it as
+fn find_dep<'a>(
+    mut deps: impl Iterator<Item = &'a Dependency>,
+    name: &str,
+) -> Option<&'a Dependency> {
+    deps.find(|dep| dep.key() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features() -> Features {
+        Features::from_json(serde_json::json!({
+            "features": {
+                "default": ["std"],
+                "std": ["serde?/std", "rand/std"],
+                "derive": ["serde/derive"],
+                "json": ["dep:serde_json"],
+                "logging": ["log"],
+                "rt": ["tokio?/rt", "rand?/small"],
+            },
+            "deps": [
+                { "name": "serde", "req": "1", "optional": true },
+                { "name": "serde_json", "req": "1", "optional": true },
+                { "name": "log", "req": "0.4", "optional": true },
+                { "name": "tokio", "req": "1", "optional": true },
+                { "name": "rand", "req": "0.8" },
+            ],
+        }))
+    }
+
+    fn set(list: &[&str]) -> BTreeSet<String> {
+        list.iter().map(ToString::to_string).collect()
+    }
+
+    fn dep_features(resolved: &Resolved, dep: &str) -> BTreeSet<String> {
+        resolved.dep_features.get(dep).cloned().unwrap_or_default()
+    }
+
+    #[test]
+    fn dep_entries_have_no_implicit_feature() {
+        let features = features();
+        assert!(features.hidden_deps.contains("serde_json"));
+
+        let resolved = Resolver::new(&features).resolve(["json"]).unwrap();
+        assert_eq!(resolved.features, set(&["json"]));
+        assert_eq!(resolved.optional_deps, set(&["serde_json"]));
+        assert!(resolved.dep_features.is_empty());
+    }
+
+    #[test]
+    fn dependency_features_enable_the_implicit_feature() {
+        let features = features();
+        let resolved = Resolver::new(&features).resolve(["derive"]).unwrap();
+        assert_eq!(resolved.features, set(&["derive", "serde"]));
+        assert_eq!(resolved.optional_deps, set(&["serde"]));
+        assert_eq!(dep_features(&resolved, "serde"), set(&["derive"]));
+    }
+
+    #[test]
+    fn weak_dependency_features() {
+        let features = features();
+
+        // nothing else activates serde
+        let resolved = Resolver::new(&features).resolve(["default"]).unwrap();
+        assert_eq!(resolved.features, set(&["default", "std"]));
+        assert!(resolved.optional_deps.is_empty());
+        assert_eq!(dep_features(&resolved, "serde"), set(&[]));
+        assert_eq!(dep_features(&resolved, "rand"), set(&["std"]));
+
+        // `derive` activates it, in either order
+        for enabled in [["std", "derive"], ["derive", "std"]] {
+            let resolved = Resolver::new(&features).resolve(enabled).unwrap();
+            assert_eq!(resolved.optional_deps, set(&["serde"]));
+            assert_eq!(dep_features(&resolved, "serde"), set(&["derive", "std"]));
+        }
+
+        // rand is required, so it is always active
+        let resolved = Resolver::new(&features).resolve(["rt"]).unwrap();
+        assert!(resolved.optional_deps.is_empty());
+        assert_eq!(dep_features(&resolved, "tokio"), set(&[]));
+        assert_eq!(dep_features(&resolved, "rand"), set(&["small"]));
+    }
+
+    #[test]
+    fn without_implicit_features() {
+        // older crates didn't have a feature for each optional dependency
+        let mut features = features();
+        features.features.remove("log");
+        features.implicit_features.remove("log");
+
+        let resolved = Resolver::new(&features).resolve(["logging"]).unwrap();
+        assert_eq!(resolved.optional_deps, set(&["log"]));
+
+        let resolved = Resolver::new(&features).resolve(["log"]).unwrap();
+        assert_eq!(resolved.optional_deps, set(&["log"]));
+    }
+
+    #[test]
+    fn unknown_names() {
+        let features = features();
+        let error = |entry: &str| {
+            Resolver::new(&features)
+                .resolve([entry])
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error("nope"),
+            "test = 1.0.0 has no feature or optional dependency named 'nope'"
+        );
+        assert_eq!(
+            error("dep:rand"),
+            "test = 1.0.0 has no feature or optional dependency named 'rand'"
+        );
+        assert_eq!(
+            error("nope/std"),
+            "test = 1.0.0 has no dependency named 'nope'"
+        );
+        assert_eq!(
+            error("nope?/std"),
+            "test = 1.0.0 has no dependency named 'nope'"
+        );
+    }
+
+    fn why(features: serde_json::Value, target: &str) -> Vec<Vec<String>> {
+        let features = Features::from_json(features);
+        let mut paths = Resolver::new(&features).why(target).unwrap().paths();
+        paths.sort();
+        paths
+    }
+
+    fn path(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn cycle() {
+        let features = serde_json::json!({ "features": { "a": ["b"], "b": ["a"] } });
+        assert_eq!(why(features.clone(), "a"), [path(&["b", "a"])]);
+        assert_eq!(why(features, "b"), [path(&["a", "b"])]);
+    }
+
+    #[test]
+    fn multiple_paths() {
+        let features = serde_json::json!({
+            "features": {
+                "default": ["std"],
+                "full": ["std", "derive"],
+                "everything": ["full"],
+                "std": [],
+                "derive": [],
+            }
+        });
+        assert_eq!(
+            why(features.clone(), "std"),
+            [
+                path(&["default", "std"]),
+                path(&["everything", "full", "std"])
+            ]
+        );
+        assert_eq!(why(features, "everything"), [path(&["everything"])]);
+    }
+
+    #[test]
+    fn optional_dependency() {
+        let features = serde_json::json!({
+            "features": {
+                "json": ["dep:serde_json"],
+                "raw": ["serde_json/raw_value"],
+                "weak": ["serde_json?/std"],
+            },
+            "deps": [{ "name": "serde_json", "req": "1", "optional": true }],
+        });
+
+        // a weak entry doesn't activate it
+        assert_eq!(
+            why(features, "serde_json"),
+            [path(&["json", "serde_json"]), path(&["raw", "serde_json"])]
+        );
+    }
+
+    #[test]
+    fn unknown_target() {
+        let features = Features::from_json(serde_json::json!({ "features": { "std": [] } }));
+        assert!(Resolver::new(&features).why("nope").is_err());
+    }
+}
@@ -0,0 +1,970 @@
+use crate::{features::Features, resolve::Resolver};
+use anyhow::Context as _;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+/// How a feature is used in the source
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageKind {
+    /// `#[cfg(..)]` on an item, statement or field
+    Cfg,
+    /// `#![cfg(..)]`, on a whole file
+    InnerCfg,
+    /// `#[cfg_attr(..)]`, which only gates an attribute
+    CfgAttr,
+    /// `#![cfg_attr(..)]`, which only gates an attribute of a whole file
+    InnerCfgAttr,
+    /// `cfg!(..)`, in an expression
+    CfgMacro,
+}
+
+/// A place in the source where a feature is used
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct FeatureUsage {
+    /// The file, relative to the crate
+    pub file: PathBuf,
+    /// The line of the attribute or macro
+    pub line: usize,
+    /// How the feature is used
+    pub kind: UsageKind,
+    /// The whole predicate, e.g. `all(feature = "std", not(feature = "alloc"))`
+    pub predicate: String,
+    /// Whether the feature is inside of a `not(..)`, so the code is used when the feature is disabled
+    pub negated: bool,
+    /// What is gated, e.g. `mod de` or `fn from_reader`, if it is known
+    pub item: Option<String>,
+    /// The module files that are gated along with the item, for a `mod name;`
+    pub files: Vec<PathBuf>,
+    /// How many lines are gated, including the module files
+    ///
+    /// This is zero for `cfg_attr` and `cfg!`, which don't remove any code
+    pub lines: usize,
+}
+
+/// Where each feature of a crate is used in its `src/` directory
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SourceScan {
+    /// The name of the crate
+    pub name: String,
+    /// The version of the crate
+    pub version: String,
+    /// Every feature used in the source, and where it is used
+    pub usages: BTreeMap<String, Vec<FeatureUsage>>,
+    /// Features that are used in the source, but not declared in the `Cargo.toml`
+    pub undeclared: BTreeSet<String>,
+    /// Features that are declared, but that nothing in the source uses
+    ///
+    /// Features that activate an optional dependency, enable a dependency feature,
+    /// or enable another feature that is used, aren't included
+    pub unused: BTreeSet<String>,
+}
+
+impl SourceScan {
+    /// Scan the `src/` directory of the crate in this directory
+    pub fn scan(features: &Features, dir: &Path) -> anyhow::Result<Self> {
+        let src = dir.join("src");
+        anyhow::ensure!(
+            src.is_dir(),
+            "cannot find the source for {} = {} in '{}'",
+            features.name,
+            features.version,
+            dir.display()
+        );
+
+        let mut files = vec![];
+        find_files(&src, &mut files)?;
+        files.sort();
+
+        let mut usages = BTreeMap::<_, Vec<_>>::new();
+        for file in &files {
+            let data = std::fs::read_to_string(file)
+                .with_context(|| anyhow::anyhow!("cannot read {}", file.display()))?;
+            for (name, usage) in scan_file(file, &data) {
+                usages.entry(name).or_default().push(FeatureUsage {
+                    file: relative(dir, file),
+                    files: usage.files.iter().map(|file| relative(dir, file)).collect(),
+                    ..usage
+                })
+            }
+        }
+
+        let undeclared = usages
+            .keys()
+            .filter(|name| !features.features.contains_key(*name))
+            .cloned()
+            .collect();
+
+        let unused = features
+            .features
+            .keys()
+            .filter(|&name| name != "default" && !usages.contains_key(name))
+            .filter(
+                |name| match Resolver::new(features).resolve(std::iter::once(name.as_str())) {
+                    Ok(resolved) => {
+                        resolved.optional_deps.is_empty()
+                            && resolved.dep_features.is_empty()
+                            && !resolved.features.iter().any(|f| usages.contains_key(f))
+                    }
+                    Err(..) => false,
+                },
+            )
+            .cloned()
+            .collect();
+
+        Ok(Self {
+            name: features.name.clone(),
+            version: features.version.clone(),
+            usages,
+            undeclared,
+            unused,
+        })
+    }
+
+    /// How many lines this feature gates, across every usage
+    pub fn gated_lines(&self, feature: &str) -> usize {
+        self.usages
+            .get(feature)
+            .into_iter()
+            .flatten()
+            .map(|usage| usage.lines)
+            .sum()
+    }
+}
+
+fn relative(dir: &Path, file: &Path) -> PathBuf {
+    file.strip_prefix(dir).unwrap_or(file).to_path_buf()
+}
+
+fn find_files(dir: &Path, out: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in dir
+        .read_dir()
+        .with_context(|| anyhow::anyhow!("cannot read {}", dir.display()))?
+        .flatten()
+    {
+        let path = entry.path();
+        if path.is_dir() {
+            find_files(&path, out)?;
+        } else if path.extension().filter(|&ext| ext == "rs").is_some() {
+            out.push(path)
+        }
+    }
+    Ok(())
+}
+
+/// Find every feature used in this file
+fn scan_file(file: &Path, data: &str) -> Vec<(String, FeatureUsage)> {
+    let src = data.as_bytes();
+    let code = mask(src);
+    let line_of = |pos: usize| src[..pos].iter().filter(|&&c| c == b'\n').count() + 1;
+
+    let mut out = vec![];
+    let mut pos = 0;
+    while pos < code.len() {
+        let (kind, start, args) = match find_cfg(&code, pos) {
+            Some(found) => found,
+            None => break,
+        };
+        let close = match matching(&code, args) {
+            Some(close) => close,
+            None => break,
+        };
+        pos = close + 1;
+
+        let mut predicate = data[args + 1..close].trim();
+        if matches!(kind, UsageKind::CfgAttr | UsageKind::InnerCfgAttr) {
+            let comma = top_level_comma(&code[args + 1..close]).map(|i| args + 1 + i);
+            predicate = data[args + 1..comma.unwrap_or(close)].trim();
+        }
+
+        let names = feature_names(predicate);
+        if names.is_empty() {
+            continue;
+        }
+
+        let (item, files, lines) = match kind {
+            UsageKind::InnerCfg => (Some("file".to_string()), vec![], data.lines().count()),
+            UsageKind::InnerCfgAttr => (Some("file".to_string()), vec![], 0),
+            UsageKind::CfgMacro => (None, vec![], 0),
+            UsageKind::Cfg | UsageKind::CfgAttr => {
+                let attr_end = match code[close..].iter().position(|&c| c == b']') {
+                    Some(i) => close + i + 1,
+                    None => break,
+                };
+                let gated = gated_item(&code, data, attr_end);
+                match (kind, gated) {
+                    (UsageKind::CfgAttr, gated) => (gated.map(|(item, ..)| item), vec![], 0),
+                    (_, Some((item, end, path))) => {
+                        // only `mod name;` has its own files
+                        let files = match src[end] {
+                            b';' => {
+                                let (inline, pending) = module_context(&code, data, start);
+                                let path = path.or(pending);
+                                module_files(file, &inline, &item, path.as_deref())
+                            }
+                            _ => vec![],
+                        };
+                        let lines = line_of(end) - line_of(start)
+                            + 1
+                            + files.iter().map(|file| count_lines(file)).sum::<usize>();
+                        (Some(item), files, lines)
+                    }
+                    (_, None) => (None, vec![], 0),
+                }
+            }
+        };
+
+        for (name, negated) in names {
+            out.push((
+                name,
+                FeatureUsage {
+                    file: file.to_path_buf(),
+                    line: line_of(start),
+                    kind,
+                    predicate: collapse(predicate),
+                    negated,
+                    item: item.clone(),
+                    files: files.clone(),
+                    lines,
+                },
+            ))
+        }
+    }
+    out
+}
+
+/// Copy the source, replacing comments and the insides of string and char literals with spaces
+///
+/// This keeps every offset (and newline) the same, so positions in it are positions in the source
+fn mask(src: &[u8]) -> Vec<u8> {
+    let mut out = src.to_vec();
+    let blank = |out: &mut Vec<u8>, range: std::ops::Range<usize>| {
+        for c in &mut out[range] {
+            if *c != b'\n' {
+                *c = b' '
+            }
+        }
+    };
+
+    let mut i = 0;
+    while i < src.len() {
+        match src[i] {
+            b'/' if src.get(i + 1) == Some(&b'/') => {
+                let end = src[i..]
+                    .iter()
+                    .position(|&c| c == b'\n')
+                    .map_or(src.len(), |n| i + n);
+                blank(&mut out, i..end);
+                i = end;
+            }
+            b'/' if src.get(i + 1) == Some(&b'*') => {
+                let (mut depth, mut end) = (0, i);
+                while end < src.len() {
+                    match (src[end], src.get(end + 1)) {
+                        (b'/', Some(b'*')) => (depth, end) = (depth + 1, end + 2),
+                        (b'*', Some(b'/')) => {
+                            (depth, end) = (depth - 1, end + 2);
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => end += 1,
+                    }
+                }
+                let end = end.min(src.len());
+                blank(&mut out, i..end);
+                i = end;
+            }
+            b'r' if is_raw_string(src, i) => {
+                let hashes = src[i + 1..].iter().take_while(|&&c| c == b'#').count();
+                let open = i + 1 + hashes;
+                let mut end = open + 1;
+                while end < src.len() {
+                    if src[end] == b'"'
+                        && src[end + 1..].iter().take_while(|&&c| c == b'#').count() >= hashes
+                    {
+                        break;
+                    }
+                    end += 1;
+                }
+                blank(&mut out, open + 1..end.min(src.len()));
+                i = end + 1 + hashes;
+            }
+            b'"' => {
+                let mut end = i + 1;
+                while end < src.len() && src[end] != b'"' {
+                    end += if src[end] == b'\\' { 2 } else { 1 };
+                }
+                blank(&mut out, i + 1..end.min(src.len()));
+                i = end + 1;
+            }
+            b'\'' => {
+                // a char literal, rather than a lifetime
+                let rest = &src[i + 1..src.len().min(i + 5)];
+                let len = match rest.first() {
+                    Some(b'\\') => src[i + 2..].iter().position(|&c| c == b'\'').map(|n| n + 2),
+                    Some(..) => std::str::from_utf8(rest)
+                        .or_else(|err| std::str::from_utf8(&rest[..err.valid_up_to()]))
+                        .ok()
+                        .and_then(|s| s.chars().next())
+                        .map(|c| c.len_utf8() + 1)
+                        .filter(|&n| src.get(i + n) == Some(&b'\'')),
+                    None => None,
+                };
+                match len {
+                    Some(len) => {
+                        blank(&mut out, i + 1..i + len);
+                        i += len + 1;
+                    }
+                    None => i += 1,
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    out
+}
+
+fn is_raw_string(src: &[u8], i: usize) -> bool {
+    let prev_is_ident = i > 0 && is_ident(src[i - 1]) && src[i - 1] != b'b';
+    let hashes = src[i + 1..].iter().take_while(|&&c| c == b'#').count();
+    !prev_is_ident && src.get(i + 1 + hashes) == Some(&b'"')
+}
+
+fn is_ident(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+fn skip_whitespace(code: &[u8], mut pos: usize) -> usize {
+    while pos < code.len() && code[pos].is_ascii_whitespace() {
+        pos += 1
+    }
+    pos
+}
+
+/// Find the next `#[cfg(`, `#![cfg(`, `#[cfg_attr(` or `cfg!(`
+///
+/// This returns the kind, where it starts, and the position of its opening paren
+fn find_cfg(code: &[u8], mut pos: usize) -> Option<(UsageKind, usize, usize)> {
+    while pos < code.len() {
+        let start = pos + code[pos..].windows(3).position(|w| w == b"cfg")?;
+        pos = start + 3;
+        if start > 0 && is_ident(code[start - 1]) {
+            continue;
+        }
+
+        let (macro_, attr, after) = if code[pos..].starts_with(b"_attr") {
+            (false, true, pos + 5)
+        } else if code.get(pos) == Some(&b'!') {
+            (true, false, pos + 1)
+        } else {
+            (false, false, pos)
+        };
+        if code.get(after).copied().is_some_and(is_ident) {
+            continue;
+        }
+
+        let paren = skip_whitespace(code, after);
+        if code.get(paren) != Some(&b'(') {
+            continue;
+        }
+        if macro_ {
+            return Some((UsageKind::CfgMacro, start, paren));
+        }
+
+        // walk backwards over `#`, `!` and `[`
+        let mut back = start;
+        let mut saw = (false, false);
+        while back > 0 {
+            back -= 1;
+            match code[back] {
+                c if c.is_ascii_whitespace() => {}
+                b'[' if !saw.0 => saw.0 = true,
+                b'!' if saw.0 && !saw.1 => saw.1 = true,
+                b'#' if saw.0 => {
+                    let kind = match (attr, saw.1) {
+                        (true, true) => UsageKind::InnerCfgAttr,
+                        (true, false) => UsageKind::CfgAttr,
+                        (false, true) => UsageKind::InnerCfg,
+                        (false, false) => UsageKind::Cfg,
+                    };
+                    return Some((kind, back, paren));
+                }
+                _ => break,
+            }
+        }
+    }
+    None
+}
+
+/// Find the closing bracket for the opening bracket at `open`
+fn matching(code: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, &c) in code.iter().enumerate().skip(open) {
+        match c {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn top_level_comma(code: &[u8]) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, &c) in code.iter().enumerate() {
+        match c {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            b',' if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Find every `feature = "name"` in a cfg predicate, and whether it is negated
+fn feature_names(predicate: &str) -> Vec<(String, bool)> {
+    let bytes = predicate.as_bytes();
+    let mut stack = vec![];
+    let mut out = vec![];
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if is_ident(c) {
+            let end = i + bytes[i..].iter().take_while(|&&c| is_ident(c)).count();
+            let ident = &predicate[i..end];
+            let next = skip_whitespace(bytes, end);
+            match bytes.get(next) {
+                Some(b'(') => {
+                    stack.push(ident);
+                    i = next + 1;
+                    continue;
+                }
+                Some(b'=') if ident == "feature" => {
+                    let quote = skip_whitespace(bytes, next + 1);
+                    if bytes.get(quote) == Some(&b'"') {
+                        if let Some(len) = predicate[quote + 1..].find('"') {
+                            let negated = stack.iter().filter(|&&s| s == "not").count() % 2 == 1;
+                            out.push((predicate[quote + 1..quote + 1 + len].to_string(), negated));
+                            i = quote + len + 2;
+                            continue;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i = end;
+            continue;
+        }
+
+        if c == b')' {
+            stack.pop();
+        }
+        if c == b'"' {
+            // skip over the values of other keys, e.g. target_os = "linux"
+            i += predicate[i + 1..].find('"').map_or(bytes.len(), |n| n + 2);
+            continue;
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Find what an attribute, that ends at `pos`, is attached to
+///
+/// This returns a description of the item, where it ends, and the `#[path]` found among the
+/// attributes after it
+fn gated_item(code: &[u8], data: &str, mut pos: usize) -> Option<(String, usize, Option<String>)> {
+    // skip over any other attributes
+    let mut path = None;
+    loop {
+        pos = skip_whitespace(code, pos);
+        if code.get(pos) != Some(&b'#') {
+            break;
+        }
+        let open = skip_whitespace(code, pos + 1);
+        let close = matching(code, open)?;
+        if code[open] == b'[' {
+            path = path_attribute(code, data, open + 1, close).or(path);
+        }
+        pos = close + 1;
+    }
+    let start = pos;
+
+    let mut depth = 0usize;
+    let mut header_end = None;
+    let mut end = code.len();
+    for (i, &c) in code.iter().enumerate().skip(start) {
+        match c {
+            b'{' if depth == 0 => {
+                header_end.get_or_insert(i);
+                depth += 1
+            }
+            b'(' | b'[' | b'{' => depth += 1,
+            b'}' if depth == 1 => {
+                depth = 0;
+                // a block ends the item, unless it continues (e.g. `if .. {} else {}` or `{ .. };`)
+                let next = skip_whitespace(code, i + 1);
+                if !matches!(code.get(next), Some(b'.' | b';' | b',' | b'?'))
+                    && !code[next..].starts_with(b"else")
+                {
+                    end = i + 1;
+                    break;
+                }
+            }
+            b')' | b']' | b'}' => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                // the end of the enclosing block
+                None => {
+                    end = i;
+                    break;
+                }
+            },
+            b';' | b',' if depth == 0 => {
+                end = i + 1;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let item = collapse(&data[start..end]);
+    let item = item.trim_end_matches([';', ',']).trim();
+    let header = collapse(&data[start..header_end.unwrap_or(end).min(end)]);
+    let header = header.trim_end_matches([';', ',']).trim();
+    Some((
+        describe(header, item),
+        end.saturating_sub(1).max(start),
+        path,
+    ))
+}
+
+/// The value of a `path = "..."` attribute, between the brackets at `start` and `end`
+fn path_attribute(code: &[u8], data: &str, start: usize, end: usize) -> Option<String> {
+    let pos = skip_whitespace(code, start);
+    if !code[pos..end].starts_with(b"path") {
+        return None;
+    }
+    let eq = skip_whitespace(code, pos + 4);
+    if code.get(eq) != Some(&b'=') {
+        return None;
+    }
+    let quote = skip_whitespace(code, eq + 1);
+    if code.get(quote) != Some(&b'"') {
+        return None;
+    }
+    let len = data[quote + 1..end].find('"')?;
+    Some(data[quote + 1..quote + 1 + len].to_string())
+}
+
+/// Find the inline modules (`mod name { .. }`) that enclose `end`, outermost first, and the
+/// `#[path]` of the item that starts at `end`, if one of its attributes before `end` has one
+///
+/// Each module is the directory that the `mod name;` declarations inside of it are found in,
+/// which is its `#[path]` if it has one
+fn module_context(code: &[u8], data: &str, end: usize) -> (Vec<String>, Option<String>) {
+    let mut modules = vec![];
+    let mut depth = 0;
+    // the `#[path]` and `mod name` of the item that is being read
+    let mut path = None;
+    let mut module = None;
+
+    let mut i = 0;
+    while i < end {
+        match code[i] {
+            b'#' => {
+                let mut open = skip_whitespace(code, i + 1);
+                if code.get(open) == Some(&b'!') {
+                    open = skip_whitespace(code, open + 1);
+                }
+                match matching(code, open).filter(|_| code.get(open) == Some(&b'[')) {
+                    Some(close) => {
+                        path = path_attribute(code, data, open + 1, close).or(path);
+                        i = close + 1;
+                    }
+                    None => i += 1,
+                }
+            }
+            b'{' => {
+                depth += 1;
+                let path = path.take();
+                if let Some(name) = module.take() {
+                    modules.push((path.unwrap_or(name), depth));
+                }
+                i += 1;
+            }
+            b'}' => {
+                modules.retain(|&(_, d)| d < depth);
+                depth = usize::saturating_sub(depth, 1);
+                (path, module) = (None, None);
+                i += 1;
+            }
+            b';' => {
+                (path, module) = (None, None);
+                i += 1;
+            }
+            c if is_ident(c) => {
+                let stop = i + code[i..].iter().take_while(|&&c| is_ident(c)).count();
+                if &code[i..stop] == b"mod" {
+                    let name = skip_whitespace(code, stop);
+                    let len = code[name..].iter().take_while(|&&c| is_ident(c)).count();
+                    if len > 0 {
+                        module = Some(data[name..name + len].to_string());
+                    }
+                }
+                i = stop;
+            }
+            _ => i += 1,
+        }
+    }
+
+    let modules = modules.into_iter().map(|(module, _)| module).collect();
+    (modules, path)
+}
+
+/// Describe an item from its header, e.g. `pub(crate) fn foo<T>(x: T) -> T` is `fn foo`
+fn describe(header: &str, item: &str) -> String {
+    const KEYWORDS: [&str; 10] = [
+        "mod",
+        "fn",
+        "struct",
+        "enum",
+        "union",
+        "trait",
+        "type",
+        "const",
+        "static",
+        "macro_rules!",
+    ];
+
+    let words = header.split_whitespace().collect::<Vec<_>>();
+    for (i, word) in words.iter().enumerate() {
+        if *word == "impl" || word.starts_with("impl<") {
+            return header[header.find(word).unwrap_or(0)..].to_string();
+        }
+        if *word == "crate" && i > 0 && words[i - 1] == "extern" {
+            return format!("extern crate {}", words.get(i + 1).unwrap_or(&""));
+        }
+        if *word == "use" {
+            return item[item.find("use").unwrap_or(0)..].to_string();
+        }
+        if *word == "const" && words.get(i + 1) == Some(&"fn") {
+            continue;
+        }
+        if KEYWORDS.contains(word) {
+            let name = words
+                .get(i + 1)
+                .and_then(|name| {
+                    name.split(|c: char| !c.is_alphanumeric() && c != '_')
+                        .next()
+                })
+                .unwrap_or_default();
+            return format!("{} {}", word, name);
+        }
+    }
+
+    // a statement, field, variant or match arm
+    const MAX: usize = 60;
+    match header.char_indices().nth(MAX) {
+        Some((i, _)) => format!("{}...", &header[..i]),
+        None => header.to_string(),
+    }
+}
+
+/// Find the files for a `mod name;` declared in this file, inside of these inline modules
+///
+/// A `#[path]` on the module is used instead of its name
+fn module_files(file: &Path, inline: &[String], item: &str, path: Option<&str>) -> Vec<PathBuf> {
+    let name = match item.strip_prefix("mod ") {
+        Some(name) => name,
+        None => return vec![],
+    };
+    let parent = match file.parent() {
+        Some(parent) => parent,
+        None => return vec![],
+    };
+
+    let dir = match file.file_stem().and_then(|s| s.to_str()) {
+        Some("mod" | "lib" | "main") => parent.to_path_buf(),
+        Some(stem) => parent.join(stem),
+        None => return vec![],
+    };
+    let dir = inline.iter().fold(dir, |dir, module| dir.join(module));
+
+    if let Some(path) = path {
+        // outside of an inline module, the path is relative to the directory of this file
+        let module = match inline.is_empty() {
+            true => parent.join(path),
+            false => dir.join(path),
+        };
+        if !module.is_file() {
+            return vec![];
+        }
+
+        // a `mod.rs` owns the rest of its directory
+        let mut files = vec![];
+        if module
+            .file_name()
+            .filter(|&name| name == "mod.rs")
+            .is_some()
+        {
+            if let Some(module_dir) = module.parent() {
+                let _ = find_files(module_dir, &mut files);
+                files.retain(|file| *file != module);
+                files.sort();
+            }
+        }
+        files.insert(0, module);
+        return files;
+    }
+
+    let mut files = vec![];
+    let module_dir = dir.join(name);
+    if module_dir.is_dir() {
+        let _ = find_files(&module_dir, &mut files);
+        files.sort();
+    }
+
+    let module = dir.join(format!("{}.rs", name));
+    if module.is_file() {
+        files.insert(0, module);
+    }
+    files
+}
+
+fn count_lines(file: &Path) -> usize {
+    std::fs::read_to_string(file)
+        .map(|data| data.lines().count())
+        .unwrap_or_default()
+}
+
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(data: &str) -> Vec<(String, UsageKind, bool, Option<String>, usize)> {
+        scan_file(Path::new("src/lib.rs"), data)
+            .into_iter()
+            .map(|(name, usage)| (name, usage.kind, usage.negated, usage.item, usage.lines))
+            .collect()
+    }
+
+    fn names(data: &str) -> Vec<String> {
+        scan(data).into_iter().map(|(name, ..)| name).collect()
+    }
+
+    #[test]
+    fn usage_kinds() {
+        let data = r#"#![cfg(feature = "a")]
+#![cfg_attr(feature = "b", no_std)]
+
+#[cfg(feature = "c")]
+fn foo() {
+    let x = 1;
+}
+
+#[cfg_attr(feature = "d", derive(Debug))]
+struct Bar;
+
+fn baz() -> bool {
+    cfg!(feature = "e")
+}
+"#;
+        assert_eq!(
+            scan(data),
+            vec![
+                (
+                    "a".into(),
+                    UsageKind::InnerCfg,
+                    false,
+                    Some("file".into()),
+                    14
+                ),
+                (
+                    "b".into(),
+                    UsageKind::InnerCfgAttr,
+                    false,
+                    Some("file".into()),
+                    0
+                ),
+                ("c".into(), UsageKind::Cfg, false, Some("fn foo".into()), 4),
+                (
+                    "d".into(),
+                    UsageKind::CfgAttr,
+                    false,
+                    Some("struct Bar".into()),
+                    0
+                ),
+                ("e".into(), UsageKind::CfgMacro, false, None, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn predicates() {
+        let data = r#"
+#[cfg(not(feature = "a"))]
+use a::A;
+#[cfg(all(feature = "b", not(feature = "c"), target_os = "linux"))]
+use b::B;
+#[cfg(any(feature = "d", not(not(feature = "e"))))]
+use d::D;
+"#;
+        let negated = scan(data)
+            .into_iter()
+            .map(|(name, _, negated, ..)| (name, negated))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            negated,
+            vec![
+                ("a".into(), true),
+                ("b".into(), false),
+                ("c".into(), true),
+                ("d".into(), false),
+                ("e".into(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn cfg_attr_predicate() {
+        let data = r#"
+#[cfg_attr(all(feature = "a", feature = "b"), doc = "feature = \"c\"", cfg(feature = "d"))]
+fn foo() {}
+"#;
+        assert_eq!(names(data), ["a", "b"]);
+
+        let usage = scan_file(Path::new("src/lib.rs"), data).remove(0).1;
+        assert_eq!(usage.predicate, r#"all(feature = "a", feature = "b")"#);
+    }
+
+    #[test]
+    fn ignores_strings_and_comments() {
+        let data = r##"
+// #[cfg(feature = "line")]
+/* #[cfg(feature = "block")] /* nested */ #[cfg(feature = "after_nested")] */
+/// #[cfg(feature = "doc")]
+const A: &str = "#[cfg(feature = \"string\")]";
+const B: &str = r#"#[cfg(feature = "raw")]"#;
+const C: &[u8] = br"cfg!(feature = "raw_bytes")";
+#[cfg(feature = "real")]
+fn real() {}
+"##;
+        assert_eq!(names(data), ["real"]);
+    }
+
+    #[test]
+    fn char_literals_and_lifetimes() {
+        let data = r#"
+fn quote<'a>(s: &'a str) -> bool {
+    s.contains('\'') || s.contains('"') || s.contains('#')
+}
+#[cfg(feature = "a")]
+fn after<'b>(c: char) -> bool {
+    c == 'ä' || c == '\u{1F600}'
+}
+#[cfg(feature = "b")]
+fn last() {}
+"#;
+        let items = scan(data)
+            .into_iter()
+            .map(|(name, _, _, item, lines)| (name, item, lines))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![
+                ("a".into(), Some("fn after".into()), 4),
+                ("b".into(), Some("fn last".into()), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn gated_items() {
+        let data = r#"
+struct Foo {
+    #[cfg(feature = "a")]
+    field: Vec<u8>,
+    other: u8,
+}
+fn foo() {
+    #[cfg(feature = "b")]
+    if true {
+        bar();
+    } else {
+        baz();
+    }
+}
+#[cfg(feature = "c")]
+#[inline]
+pub(crate) const fn qux<T>(x: T) -> T { x }
+#[cfg(feature = "d")]
+impl<T> Foo for T {}
+"#;
+        let items = scan(data)
+            .into_iter()
+            .map(|(name, _, _, item, lines)| (name, item, lines))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![
+                ("a".into(), Some("field: Vec<u8>".into()), 2),
+                ("b".into(), Some("if true".into()), 6),
+                ("c".into(), Some("fn qux".into()), 3),
+                ("d".into(), Some("impl<T> Foo for T".into()), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn module_paths() {
+        let data = r#"
+#[path = "outer.rs"]
+#[cfg(feature = "a")]
+mod a;
+
+mod gen {
+    #[cfg(feature = "b")]
+    #[path = "gen_b.rs"]
+    mod b;
+
+    #[path = "other"]
+    mod inner {
+        #[cfg(feature = "c")]
+        mod c;
+    }
+}
+"#;
+        let code = mask(data.as_bytes());
+        let at = |needle: &str| data.find(needle).unwrap();
+
+        let (inline, path) = module_context(&code, data, at("#[cfg(feature = \"a\")]"));
+        assert!(inline.is_empty());
+        assert_eq!(path.as_deref(), Some("outer.rs"));
+
+        let (inline, path) = module_context(&code, data, at("#[cfg(feature = \"b\")]"));
+        assert_eq!(inline, ["gen"]);
+        assert_eq!(path, None);
+        let (.., path) = gated_item(&code, data, at("#[path = \"gen_b.rs\"]")).unwrap();
+        assert_eq!(path.as_deref(), Some("gen_b.rs"));
+
+        let (inline, path) = module_context(&code, data, at("#[cfg(feature = \"c\")]"));
+        assert_eq!(inline, ["gen", "other"]);
+        assert_eq!(path, None);
+    }
+}
@@ -0,0 +1,49 @@
+use cargo_platform::{Cfg, Platform};
+use std::str::FromStr;
+
+/// The `cfg` values of a target, used to decide which target-specific dependencies apply to it
+#[derive(Debug, Clone)]
+pub struct Target {
+    /// The target triple, e.g. `x86_64-pc-windows-msvc`
+    pub triple: String,
+    cfgs: Vec<Cfg>,
+}
+
+impl Target {
+    /// Get the `cfg` values for this target triple from `rustc --print cfg`
+    ///
+    /// This uses the `RUSTC` environment variable to find rustc, if its set
+    pub fn from_rustc(triple: &str) -> anyhow::Result<Self> {
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let output = std::process::Command::new(rustc)
+            .args(["--print", "cfg", "--target", triple])
+            .output()
+            .map_err(|err| anyhow::anyhow!("cannot run rustc: {}", err))?;
+
+        anyhow::ensure!(
+            output.status.success(),
+            "cannot get the cfg values for '{}': {}",
+            triple,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+
+        let cfgs = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(Cfg::from_str)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            triple: triple.to_string(),
+            cfgs,
+        })
+    }
+
+    /// Does this dependency target (a triple, or a `cfg(..)` expression) apply to this target?
+    ///
+    /// Anything that cannot be parsed is assumed to apply
+    pub fn matches(&self, target: &str) -> bool {
+        Platform::from_str(target)
+            .map(|platform| platform.matches(&self.triple, &self.cfgs))
+            .unwrap_or(true)
+    }
+}
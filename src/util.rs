@@ -1,18 +1,24 @@
-use std::path::PathBuf;
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+};
 
-/// Extract the crate to the cache directory
+/// The directory, inside of the cache directory, where crates from other registries are cached
+pub const REGISTRIES_DIR: &str = "registries";
+
+/// Extract the crate to this cache directory
 pub fn extract_crate(
+    base: &Path,
     data: &[u8],
     crate_name: &str,
     crate_version: &str,
 ) -> anyhow::Result<PathBuf> {
     use {flate2::bufread::GzDecoder, std::io::BufReader, tar::Archive};
 
-    let base = cache_dir()?;
     let target = base.join(format!("{}-{}", crate_name, crate_version));
     // avoid unpacking if it already exists
     if !target.is_dir() {
-        Archive::new(GzDecoder::new(BufReader::new(data))).unpack(&base)?;
+        Archive::new(GzDecoder::new(BufReader::new(data))).unpack(base)?;
     }
     Ok(target)
 }
@@ -24,8 +30,14 @@ pub fn cache_dir() -> anyhow::Result<PathBuf> {
         .map(|dir| dir.cache_dir().into())
 }
 
+/// Get the config directory
+pub fn config_dir() -> anyhow::Result<PathBuf> {
+    directories::ProjectDirs::from("com.github", "museun", "whatfeatures")
+        .ok_or_else(|| anyhow::anyhow!("cannot open projects directory"))
+        .map(|dir| dir.config_dir().into())
+}
+
 /// This is the name chunking schemed used by crates.io
-#[allow(dead_code)]
 pub fn chunk_name(name: &str) -> String {
     assert!(!name.is_empty());
     match name.len() {
@@ -35,3 +47,207 @@ pub fn chunk_name(name: &str) -> String {
         _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
     }
 }
+
+/// Compare two versions by their semver precedence, falling back to comparing them as strings
+pub fn compare_versions(left: &str, right: &str) -> Ordering {
+    match (semver::Version::parse(left), semver::Version::parse(right)) {
+        (Ok(left), Ok(right)) => left.cmp(&right),
+        (Ok(..), Err(..)) => Ordering::Greater,
+        (Err(..), Ok(..)) => Ordering::Less,
+        (Err(..), Err(..)) => left.cmp(right),
+    }
+}
+
+/// Whether this is an exact version (e.g. `1.0.3`) rather than a requirement (e.g. `1`, `~1.2` or `>=0.5, <0.7`)
+pub fn is_exact_version(semver: &str) -> bool {
+    semver::Version::parse(semver).is_ok()
+}
+
+/// Parse a `rust-version` (e.g. `1.70` or `1.70.0`), ignoring any pre-release (e.g. `1.82.0-nightly`)
+pub fn parse_rust_version(version: &str) -> Option<semver::Version> {
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version.trim().split('.').map(str::parse::<u64>);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    parts
+        .next()
+        .is_none()
+        .then(|| semver::Version::new(major, minor, patch))
+}
+
+/// Get the version of the rustc found on the PATH, from `rustc --version`
+///
+/// This uses the `RUSTC` environment variable to find rustc, if its set
+pub fn rustc_version() -> Option<semver::Version> {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = std::process::Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    // e.g. rustc 1.80.0 (051478957 2024-07-21)
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .split_whitespace()
+        .nth(1)
+        .and_then(parse_rust_version)
+}
+
+/// Get the host target triple of the rustc found on the PATH, from `rustc -vV`
+///
+/// This uses the `RUSTC` environment variable to find rustc, if its set
+pub fn host_triple() -> Option<String> {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = std::process::Command::new(rustc)
+        .arg("-vV")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    // e.g. host: x86_64-unknown-linux-gnu
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(|host| host.trim().to_string())
+}
+
+/// Find the newest version that matches this requirement, the same way cargo would
+///
+/// An exact version (e.g. `1.0.3`) will only match itself. Pre-releases only match if the requirement names one
+pub fn find_matching<T, F>(
+    items: impl IntoIterator<Item = T>,
+    version: F,
+    semver: &str,
+) -> Option<T>
+where
+    F: Fn(&T) -> &str,
+{
+    if is_exact_version(semver) {
+        return items.into_iter().find(|item| version(item) == semver);
+    }
+
+    let req = semver::VersionReq::parse(semver).ok()?;
+    items
+        .into_iter()
+        .filter(|item| {
+            semver::Version::parse(version(item))
+                .map(|v| req.matches(&v))
+                .unwrap_or_default()
+        })
+        .max_by(|left, right| compare_versions(version(left), version(right)))
+}
+
+/// Find the newest version by semver precedence
+///
+/// Pre-releases are only considered if `pre_release` is set, or if there are no other versions
+pub fn find_latest<T, F>(
+    items: impl IntoIterator<Item = T>,
+    version: F,
+    pre_release: bool,
+) -> Option<T>
+where
+    F: Fn(&T) -> &str,
+{
+    let is_pre_release = |item: &T| {
+        semver::Version::parse(version(item))
+            .map(|v| !v.pre.is_empty())
+            .unwrap_or_default()
+    };
+
+    let (mut stable, mut pre) = (None::<T>, None::<T>);
+    for item in items {
+        let slot = if is_pre_release(&item) {
+            &mut pre
+        } else {
+            &mut stable
+        };
+
+        match slot {
+            Some(current) if compare_versions(version(current), version(&item)).is_ge() => {}
+            _ => *slot = Some(item),
+        }
+    }
+
+    match (stable, pre) {
+        (Some(stable), Some(pre)) if pre_release => {
+            if compare_versions(version(&pre), version(&stable)).is_gt() {
+                Some(pre)
+            } else {
+                Some(stable)
+            }
+        }
+        (Some(stable), ..) => Some(stable),
+        (None, pre) => pre,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERSIONS: &[(&str, bool)] = &[
+        ("0.5.0", false),
+        ("0.5.3", false),
+        ("0.6.9", false),
+        ("0.7.0", false),
+        ("1.0.0", false),
+        ("1.0.3", true),
+        ("1.1.0", false),
+        ("1.2.0", false),
+        ("1.2.5", false),
+        ("1.2.6", true),
+        ("1.3.0", false),
+        ("1.4.0-beta.1", false),
+        ("2.0.0-alpha.1", false),
+    ];
+
+    // this is how the callers skip yanked versions, unless an exact version was asked for
+    fn matching(semver: &str) -> Option<&'static str> {
+        let exact = is_exact_version(semver);
+        find_matching(
+            VERSIONS.iter().filter(|(_, yanked)| exact || !yanked),
+            |(version, _)| version,
+            semver,
+        )
+        .map(|(version, _)| *version)
+    }
+
+    #[test]
+    fn requirements() {
+        for (semver, expected) in [
+            ("1", Some("1.3.0")),
+            ("^1.1", Some("1.3.0")),
+            ("~1.2", Some("1.2.5")),
+            (">=0.5, <0.7", Some("0.6.9")),
+            ("=1.0.3", None),
+            ("=1.0.0", Some("1.0.0")),
+            ("*", Some("1.3.0")),
+            ("3", None),
+        ] {
+            assert_eq!(matching(semver), expected, "{}", semver);
+        }
+    }
+
+    #[test]
+    fn exact_versions() {
+        // an exact version can be yanked, or a pre-release
+        assert_eq!(matching("1.0.3"), Some("1.0.3"));
+        assert_eq!(matching("1.4.0-beta.1"), Some("1.4.0-beta.1"));
+        assert_eq!(matching("1.0.4"), None);
+    }
+
+    #[test]
+    fn pre_releases() {
+        assert_eq!(matching(">=1.4.0-beta.1"), Some("1.4.0-beta.1"));
+        assert_eq!(matching("^2.0.0-alpha"), Some("2.0.0-alpha.1"));
+        assert_eq!(matching("2"), None);
+    }
+
+    #[test]
+    fn invalid_requirement() {
+        assert_eq!(matching("not a version"), None);
+    }
+}